@@ -0,0 +1,532 @@
+use crate::{
+    build_9x9_regions, build_9x9_rules_with, candidates::ToCellMask, rules::rules_for_regions,
+    CandidateWord, Candidates, Engine, Gameboard, Observer, ReusableSolver, Rule, Solver,
+    SolverConfig, Trace, Variant,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    // the grade of a solve that used `techniques`, by the costliest of `rules` among
+    // them: naked singles are easy, hidden singles medium and anything beyond hard. a
+    // solve that stalled or had to guess is expert
+    #[must_use]
+    pub fn of_solve<const X: usize, const Y: usize>(
+        rules: &[Box<dyn Rule<X, Y>>],
+        techniques: &[String],
+        solved: bool,
+    ) -> Self {
+        let guessed = techniques
+            .iter()
+            .any(|technique| technique == "guess" || technique == "search");
+
+        if !solved || guessed {
+            return Self::Expert;
+        }
+
+        let costliest = rules
+            .iter()
+            .filter(|rule| techniques.iter().any(|technique| technique == rule.name()))
+            .map(|rule| rule.cost())
+            .max()
+            .unwrap_or(0);
+
+        match costliest {
+            0..=1 => Self::Easy,
+            2 => Self::Medium,
+            _ => Self::Hard,
+        }
+    }
+}
+
+impl Gameboard<9, 9> {
+    // rough grade from the givens alone, without running the solver
+    #[must_use]
+    pub fn estimate_difficulty(&self) -> Difficulty {
+        let regions = build_9x9_regions();
+
+        let mut candidates = Candidates::<9, 9>::default();
+        let mut clues = 0;
+
+        for x in 0..9 {
+            for y in 0..9 {
+                if self.cell(x, y) != 0 {
+                    candidates.mark_as_solved(x, y);
+                    clues += 1;
+                }
+            }
+        }
+
+        let mut sparsest_region = 9;
+
+        for region in &regions {
+            let mut region_clues = 0;
+
+            for (x, y) in region {
+                if self.cell(*x, *y) == 0 {
+                    continue;
+                }
+
+                region_clues += 1;
+
+                for (x2, y2) in region {
+                    candidates.exclude_candidate(*x2, *y2, self.cell(*x, *y));
+                }
+            }
+
+            sparsest_region = sparsest_region.min(region_clues);
+        }
+
+        let mut singles: Vec<(usize, usize)> = (0..9)
+            .flat_map(|x| (0..9).map(move |y| (x, y)))
+            .filter(|(x, y)| candidates.remaining_candidates(*x, *y) == 1)
+            .collect();
+
+        for region in &regions {
+            for n in 1..=9_u8 {
+                let mut positions = region
+                    .iter()
+                    .filter(|(x, y)| candidates.cells[*x][*y] & n.to_cell_mask() > 0);
+
+                if let (Some(position), None) = (positions.next(), positions.next()) {
+                    singles.push(*position);
+                }
+            }
+        }
+
+        singles.sort_unstable();
+        singles.dedup();
+
+        let mut score = match clues {
+            0..=23 => 2,
+            24..=27 => 1,
+            _ => 0,
+        };
+
+        score += match singles.len() {
+            0 => 3,
+            1..=2 => 2,
+            3..=5 => 1,
+            _ => 0,
+        };
+
+        if sparsest_region == 0 {
+            score += 1;
+        }
+
+        match score {
+            0..=1 => Difficulty::Easy,
+            2..=3 => Difficulty::Medium,
+            4..=5 => Difficulty::Hard,
+            _ => Difficulty::Expert,
+        }
+    }
+}
+
+// symmetries of the pattern of givens, ignoring which digits they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Symmetry {
+    Rotational180,
+    Rotational90,
+    // mirrored top to bottom
+    Horizontal,
+    // mirrored left to right
+    Vertical,
+    // mirrored across the top-left to bottom-right diagonal
+    Diagonal,
+    AntiDiagonal,
+}
+
+impl Symmetry {
+    pub const ALL: [Self; 6] = [
+        Self::Rotational180,
+        Self::Rotational90,
+        Self::Horizontal,
+        Self::Vertical,
+        Self::Diagonal,
+        Self::AntiDiagonal,
+    ];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rotational180 => "rotational-180",
+            Self::Rotational90 => "rotational-90",
+            Self::Horizontal => "horizontal",
+            Self::Vertical => "vertical",
+            Self::Diagonal => "diagonal",
+            Self::AntiDiagonal => "anti-diagonal",
+        }
+    }
+
+    fn map(self, (x, y): (usize, usize)) -> (usize, usize) {
+        match self {
+            Self::Rotational180 => (8 - x, 8 - y),
+            Self::Rotational90 => (y, 8 - x),
+            Self::Horizontal => (8 - x, y),
+            Self::Vertical => (x, 8 - y),
+            Self::Diagonal => (y, x),
+            Self::AntiDiagonal => (8 - y, 8 - x),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ClueStats {
+    pub clues: usize,
+    // digits that aren't given anywhere
+    pub missing_digits: Vec<u8>,
+    // givens per box, left to right then top to bottom
+    pub box_clues: [usize; 9],
+    pub symmetries: Vec<Symmetry>,
+}
+
+impl Gameboard<9, 9> {
+    #[must_use]
+    pub fn clue_stats(&self) -> ClueStats {
+        let given = |(x, y): (usize, usize)| self.cell(x, y) != 0;
+        let cells = || (0..9).flat_map(|x| (0..9).map(move |y| (x, y)));
+
+        let mut box_clues = [0; 9];
+
+        for (x, y) in cells().filter(|position| given(*position)) {
+            box_clues[x / 3 * 3 + y / 3] += 1;
+        }
+
+        let missing_digits = (1..=9)
+            .filter(|n| !self.state.iter().flatten().any(|cell| cell == n))
+            .collect();
+
+        let symmetries = Symmetry::ALL
+            .into_iter()
+            .filter(|symmetry| {
+                cells().all(|position| given(position) == given(symmetry.map(position)))
+            })
+            .collect();
+
+        ClueStats {
+            clues: box_clues.iter().sum(),
+            missing_digits,
+            box_clues,
+            symmetries,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Rating {
+    pub fingerprint: u64,
+    pub clues: usize,
+    // from the costliest technique the solve needed, see `Difficulty::of_solve`
+    pub difficulty: Difficulty,
+    // techniques that made progress, in the order they were first needed
+    pub techniques: Vec<String>,
+    pub solved: bool,
+}
+
+// everything a batch rating reports about one puzzle
+#[must_use]
+pub fn rate(gameboard: &Gameboard<9, 9>, config: SolverConfig) -> Rating {
+    rate_with(&mut Solver::reusable(config, &[]), gameboard)
+}
+
+// `rate` on a solver kept across a batch of puzzles
+pub fn rate_with(solver: &mut ReusableSolver, gameboard: &Gameboard<9, 9>) -> Rating {
+    let mut board = gameboard.clone();
+    let mut trace = Trace::default();
+
+    let stats = solver.solve(&mut board, Some(&mut trace));
+
+    let mut techniques: Vec<String> = trace.techniques().into_iter().map(String::from).collect();
+
+    // the search backend's guesses aren't observed, so they're only counted. the
+    // hybrid backend's are already there as "guess"
+    if stats.nodes > 0 && !techniques.iter().any(|technique| technique == "guess") {
+        techniques.push("search".to_string());
+    }
+
+    Rating {
+        fingerprint: gameboard.fingerprint(),
+        clues: gameboard.clue_stats().clues,
+        difficulty: Difficulty::of_solve(solver.solver().rules(), &techniques, board.is_solved()),
+        techniques,
+        solved: board.is_solved(),
+    }
+}
+
+// how many logical moves were open at each unsolved state along the solve path. a
+// narrow puzzle only ever offers one or two, and a minimum of 0 means the rules stalled
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Branching {
+    pub states: usize,
+    pub min: usize,
+    pub average: f64,
+}
+
+// rules that only pass on what placed digits say, rather than deducing anything
+pub(crate) fn is_bookkeeping<const X: usize, const Y: usize>(rule: &dyn Rule<X, Y>) -> bool {
+    matches!(rule.name(), "exclude_when_solved" | "unique_by_region")
+}
+
+// runs the bookkeeping rules until they stop changing anything
+pub(crate) fn settle<const X: usize, const Y: usize>(
+    rules: &[Box<dyn Rule<X, Y>>],
+    gameboard: &Gameboard<X, Y>,
+    candidates: &mut Candidates<X, Y>,
+) {
+    loop {
+        let before = candidates.cells;
+
+        for rule in rules.iter().filter(|rule| is_bookkeeping(rule.as_ref())) {
+            candidates.set_cause(rule.name());
+            rule.visit(gameboard, candidates);
+        }
+
+        if candidates.cells == before {
+            break;
+        }
+    }
+}
+
+// counts the moves at every state the solve passes through
+struct MoveCounter {
+    rules: Vec<Box<dyn Rule<9, 9>>>,
+    counts: Vec<usize>,
+}
+
+impl MoveCounter {
+    // once the bookkeeping rules have caught up, a move is a single waiting to be
+    // placed or a deduction some other rule would make
+    fn count(&mut self, gameboard: &Gameboard<9, 9>, candidates: &Candidates<9, 9>) {
+        if gameboard.is_solved() {
+            return;
+        }
+
+        let mut settled = candidates.clone();
+        settle(&self.rules, gameboard, &mut settled);
+
+        let deductions = self
+            .rules
+            .iter()
+            .filter(|rule| !is_bookkeeping(rule.as_ref()))
+            .filter(|rule| {
+                let mut trial = settled.clone();
+                rule.visit(gameboard, &mut trial);
+                trial.cells != settled.cells
+            })
+            .count();
+
+        self.counts
+            .push(settled.pending_singles(gameboard).len() + deductions);
+    }
+}
+
+impl Observer<9, 9> for MoveCounter {
+    fn observe(
+        &mut self,
+        _: &str,
+        _: &[[CandidateWord; 9]; 9],
+        gameboard: &Gameboard<9, 9>,
+        candidates: &Candidates<9, 9>,
+    ) {
+        self.count(gameboard, candidates);
+    }
+}
+
+#[must_use]
+pub fn branching(
+    gameboard: &Gameboard<9, 9>,
+    config: SolverConfig,
+    variants: &[Variant],
+) -> Branching {
+    let mut counter = MoveCounter {
+        rules: build_9x9_rules_with(variants),
+        counts: vec![],
+    };
+
+    let mut gameboard = gameboard.clone();
+    let mut candidates = Candidates::default();
+
+    counter.count(&gameboard, &candidates);
+
+    Solver::new(config, variants).resume(&mut gameboard, &mut candidates, Some(&mut counter));
+
+    let states = counter.counts.len();
+
+    #[allow(clippy::cast_precision_loss)]
+    let average = if states == 0 {
+        0.0
+    } else {
+        counter.counts.iter().sum::<usize>() as f64 / states as f64
+    };
+
+    Branching {
+        states,
+        min: counter.counts.iter().copied().min().unwrap_or(0),
+        average,
+    }
+}
+
+pub const MAX_BACKDOOR_SIZE: usize = 2;
+
+// smallest set of guessed cells after which singles alone finish the puzzle. the
+// pattern techniques are left out, so the size doesn't shrink as they're added
+#[must_use]
+pub fn find_backdoor(
+    gameboard: &Gameboard<9, 9>,
+    config: SolverConfig,
+    max_size: usize,
+) -> Option<Vec<(usize, usize, u8)>> {
+    let engine = Engine::new(rules_for_regions(&build_9x9_regions()), config);
+
+    let mut gameboard = gameboard.clone();
+    let mut candidates = Candidates::<9, 9>::default();
+
+    engine.run(&mut gameboard, &mut candidates, None);
+
+    if gameboard.is_solved() {
+        return Some(vec![]);
+    }
+
+    let empty_cells: Vec<(usize, usize)> = (0..9)
+        .flat_map(|x| (0..9).map(move |y| (x, y)))
+        .filter(|(x, y)| gameboard.cell(*x, *y) == 0)
+        .collect();
+
+    let mut guesses = vec![];
+
+    for size in 1..=max_size {
+        if search_backdoor(
+            &mut gameboard,
+            &mut candidates,
+            &engine,
+            &empty_cells,
+            size,
+            &mut guesses,
+        ) {
+            return Some(guesses);
+        }
+    }
+
+    None
+}
+
+// guesses are undone through the candidates' journal rather than by cloning
+fn search_backdoor(
+    gameboard: &mut Gameboard<9, 9>,
+    candidates: &mut Candidates<9, 9>,
+    engine: &Engine<9, 9>,
+    empty_cells: &[(usize, usize)],
+    size: usize,
+    guesses: &mut Vec<(usize, usize, u8)>,
+) -> bool {
+    for (i, (x, y)) in empty_cells.iter().enumerate() {
+        if gameboard.cell(*x, *y) != 0 {
+            continue;
+        }
+
+        for n in 1..=9_u8 {
+            if candidates.cells[*x][*y] & n.to_cell_mask() == 0 {
+                continue;
+            }
+
+            let mark = candidates.mark();
+
+            candidates.place(gameboard, (*x, *y), n);
+            engine.run(gameboard, candidates, None);
+
+            guesses.push((*x, *y, n));
+
+            if gameboard.is_solved() {
+                return true;
+            }
+
+            if size > 1
+                && !candidates.is_contradicted(gameboard)
+                && search_backdoor(
+                    gameboard,
+                    candidates,
+                    engine,
+                    &empty_cells[i + 1..],
+                    size - 1,
+                    guesses,
+                )
+            {
+                return true;
+            }
+
+            guesses.pop();
+            candidates.rollback_to(gameboard, mark);
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io;
+
+    const SOLUTION: &str =
+        "196287345725643981384951276832194657541736892967825413219378564658412739473569128";
+
+    #[test]
+    fn backdoors_are_the_fewest_guesses_singles_finish_from() {
+        let solvable = io::parse_ss(SOLUTION).unwrap();
+        assert_eq!(
+            find_backdoor(&solvable, SolverConfig::default(), MAX_BACKDOOR_SIZE),
+            Some(vec![])
+        );
+
+        let stalled = io::parse_ss(
+            ".....5..389.6.......7......7..4....5......3...1...27684..3....1...92..7..7....8.4",
+        )
+        .unwrap();
+        assert_eq!(
+            find_backdoor(&stalled, SolverConfig::default(), MAX_BACKDOOR_SIZE),
+            Some(vec![(0, 3, 2)])
+        );
+        assert_eq!(find_backdoor(&stalled, SolverConfig::default(), 0), None);
+    }
+
+    #[test]
+    fn rating_grades_by_the_techniques_used() {
+        // one cell missing from each row leaves nothing but naked singles
+        let mut state = io::parse_ss(SOLUTION).unwrap().state;
+        for (x, row) in state.iter_mut().enumerate() {
+            row[x] = 0;
+        }
+        let easy = Gameboard::from(state);
+        let rating = rate(&easy, SolverConfig::default());
+        assert!(rating.solved);
+        assert_eq!(rating.difficulty, Difficulty::Easy);
+
+        let patterns = io::parse_ss(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        assert_eq!(
+            rate(&patterns, SolverConfig::default()).difficulty,
+            Difficulty::Hard
+        );
+
+        // the rules can't start on an empty board
+        let empty = Gameboard::from([[0; 9]; 9]);
+        assert_eq!(
+            rate(&empty, SolverConfig::default()).difficulty,
+            Difficulty::Expert
+        );
+    }
+}
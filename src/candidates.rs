@@ -0,0 +1,387 @@
+use std::{
+    fmt::{Debug, Error, Formatter},
+    ops::{BitAnd, BitOr, RangeInclusive, Sub},
+};
+
+use crate::{Gameboard, Region};
+
+// one bit per digit, so this caps boards at 32 digits, enough for 25x25. it's the
+// same width for every board so `Observer` impls don't depend on the board size
+pub type CandidateWord = u32;
+
+// the digits of a board whose rows are `Y` cells long
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn digits<const Y: usize>() -> RangeInclusive<u8> {
+    1..=Y as u8
+}
+
+pub(crate) trait ToCellMask {
+    fn to_cell_mask(self) -> CandidateWord;
+}
+
+pub(crate) trait ToResult {
+    fn to_result(self) -> u8;
+}
+
+// read-only view of the digits still possible in one cell
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CandidateSet(pub(crate) CandidateWord);
+
+impl CandidateSet {
+    #[must_use]
+    pub fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    #[must_use]
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[must_use]
+    pub fn contains(self, digit: u8) -> bool {
+        self.0 & digit.to_cell_mask() > 0
+    }
+
+    // digits in ascending order
+    pub fn iter(self) -> impl Iterator<Item = u8> {
+        #[allow(clippy::cast_possible_truncation)]
+        (1..=CandidateWord::BITS as u8).filter(move |n| self.contains(*n))
+    }
+}
+
+// union, intersection and difference, for reasoning over groups of cells at once
+impl BitOr for CandidateSet {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitAnd for CandidateSet {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+impl Sub for CandidateSet {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JournalEntry {
+    Eliminated {
+        position: (usize, usize),
+        digits: CandidateSet,
+        cause: &'static str,
+        // the cause of the cell's elimination before this one, put back on rollback
+        previous_cause: &'static str,
+    },
+    Placed {
+        position: (usize, usize),
+        previous: u8,
+    },
+    // a given set by `place_clue`, which `Solver::update_clue` rolls back to
+    Clue {
+        position: (usize, usize),
+        previous: u8,
+    },
+}
+
+// position in the journal to roll back to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JournalMark(usize);
+
+#[derive(Clone)]
+pub struct Candidates<const X: usize, const Y: usize> {
+    pub(crate) cells: [[CandidateWord; Y]; X],
+    // only kept once something takes a mark, so plain solves don't pay for it
+    journal: Option<Vec<JournalEntry>>,
+    cause: &'static str,
+    // what the cause's latest visit found, for rules whose name doesn't say enough
+    detail: Option<String>,
+    // the cause of each cell's latest elimination, so a placement can say whether a
+    // hidden single narrowed the cell or eliminations wore it down
+    narrowed_by: [[&'static str; Y]; X],
+}
+
+// the journal is bookkeeping, so it's left out to keep the output readable
+impl<const X: usize, const Y: usize> Debug for Candidates<X, Y> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_struct("Candidates")
+            .field("cells", &self.cells)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<const X: usize, const Y: usize> Default for Candidates<X, Y> {
+    fn default() -> Self {
+        // rows are Y cells long, so the board uses digits 1 to Y
+        const {
+            assert!(
+                Y <= CandidateWord::BITS as usize,
+                "board has more digits than CandidateWord can hold"
+            );
+        };
+
+        Self {
+            cells: [[CandidateWord::MAX >> (CandidateWord::BITS as usize - Y); Y]; X],
+            journal: None,
+            cause: "manual",
+            detail: None,
+            narrowed_by: [["manual"; Y]; X],
+        }
+    }
+}
+
+impl ToCellMask for u8 {
+    fn to_cell_mask(self) -> CandidateWord {
+        1 << (self - 1)
+    }
+}
+
+impl ToResult for CandidateWord {
+    #[allow(clippy::cast_possible_truncation)]
+    fn to_result(self) -> u8 {
+        self.ilog2() as u8 + 1
+    }
+}
+
+impl<const X: usize, const Y: usize> Candidates<X, Y> {
+    pub(crate) fn apply_uniques(&mut self, gameboard: &mut Gameboard<X, Y>) -> bool {
+        let mut changes_made = false;
+
+        for x in 0..X {
+            for y in 0..Y {
+                if self.remaining_candidates(x, y) == 1 {
+                    self.place(gameboard, (x, y), self.cells[x][y].to_result());
+                    changes_made = true;
+                }
+            }
+        }
+
+        changes_made
+    }
+
+    // every candidate back for a new puzzle, keeping the journal's allocation if it has one
+    pub fn reset(&mut self) {
+        self.cells = Self::default().cells;
+        self.cause = "manual";
+        self.detail = None;
+        self.narrowed_by = [["manual"; Y]; X];
+
+        if let Some(journal) = &mut self.journal {
+            journal.clear();
+        }
+    }
+
+    // sets a cell on the board, journaled so `rollback_to` can undo it
+    pub fn place(&mut self, gameboard: &mut Gameboard<X, Y>, (x, y): (usize, usize), value: u8) {
+        if let Some(journal) = &mut self.journal {
+            journal.push(JournalEntry::Placed {
+                position: (x, y),
+                previous: gameboard.cell(x, y),
+            });
+        }
+
+        gameboard.force_cell(x, y, value);
+    }
+
+    // like `place`, but journaled as a clue so edits to it can be found again.
+    // starts the journal if nothing has taken a mark yet
+    pub fn place_clue(
+        &mut self,
+        gameboard: &mut Gameboard<X, Y>,
+        (x, y): (usize, usize),
+        value: u8,
+    ) {
+        self.journal
+            .get_or_insert_with(Vec::new)
+            .push(JournalEntry::Clue {
+                position: (x, y),
+                previous: gameboard.cell(x, y),
+            });
+
+        gameboard.force_cell(x, y, value);
+    }
+
+    // journal positions and cells of the clues placed with `place_clue`, oldest first
+    pub(crate) fn clue_marks(&self) -> impl Iterator<Item = (JournalMark, (usize, usize))> + '_ {
+        self.journal()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| match *entry {
+                JournalEntry::Clue { position, .. } => Some((JournalMark(index), position)),
+                _ => None,
+            })
+    }
+
+    // recorded against journal entries until the next call, which also drops the
+    // previous cause's detail
+    pub fn set_cause(&mut self, cause: &'static str) {
+        self.cause = cause;
+        self.detail = None;
+    }
+
+    // appended to the current cause's detail, e.g. which box an empty rectangle used
+    pub fn add_detail(&mut self, detail: &str) {
+        match &mut self.detail {
+            Some(details) if !details.split("; ").any(|seen| seen == detail) => {
+                details.push_str("; ");
+                details.push_str(detail);
+            }
+            Some(_) => {}
+            None => self.detail = Some(detail.to_string()),
+        }
+    }
+
+    // steps like apply_uniques don't set a cause, so the detail only goes with `cause`
+    // while it's still the current one
+    #[must_use]
+    pub fn detail(&self, cause: &str) -> Option<&str> {
+        self.detail.as_deref().filter(|_| self.cause == cause)
+    }
+
+    pub fn mark(&mut self) -> JournalMark {
+        JournalMark(self.journal.get_or_insert_with(Vec::new).len())
+    }
+
+    #[must_use]
+    pub fn journal(&self) -> &[JournalEntry] {
+        self.journal.as_deref().unwrap_or_default()
+    }
+
+    // undoes everything journaled since `mark`, newest first
+    pub fn rollback_to(&mut self, gameboard: &mut Gameboard<X, Y>, JournalMark(mark): JournalMark) {
+        let Some(journal) = &mut self.journal else {
+            return;
+        };
+
+        for entry in journal.drain(mark..).rev() {
+            match entry {
+                JournalEntry::Eliminated {
+                    position: (x, y),
+                    digits,
+                    previous_cause,
+                    ..
+                } => {
+                    self.cells[x][y] |= digits.0;
+                    self.narrowed_by[x][y] = previous_cause;
+                }
+                JournalEntry::Placed {
+                    position: (x, y),
+                    previous,
+                }
+                | JournalEntry::Clue {
+                    position: (x, y),
+                    previous,
+                } => {
+                    gameboard.state[x][y] = previous;
+                    gameboard
+                        .newly_solved
+                        .retain(|position| *position != (x, y));
+                }
+            }
+        }
+    }
+
+    fn remove_candidates(&mut self, x: usize, y: usize, mask: CandidateWord) {
+        let removed = self.cells[x][y] & mask;
+
+        if removed == 0 {
+            return;
+        }
+
+        if let Some(journal) = &mut self.journal {
+            journal.push(JournalEntry::Eliminated {
+                position: (x, y),
+                digits: CandidateSet(removed),
+                cause: self.cause,
+                previous_cause: self.narrowed_by[x][y],
+            });
+        }
+
+        self.cells[x][y] &= !removed;
+        self.narrowed_by[x][y] = self.cause;
+    }
+
+    pub fn exclude_candidate(&mut self, x: usize, y: usize, candidate: u8) {
+        self.remove_candidates(x, y, candidate.to_cell_mask());
+    }
+    // leaves the cell empty if `candidate` had already been excluded
+    pub fn set_exclusive_candidate(&mut self, x: usize, y: usize, candidate: u8) {
+        self.remove_candidates(x, y, !candidate.to_cell_mask());
+    }
+    #[must_use]
+    pub fn remaining_candidates(&self, x: usize, y: usize) -> u32 {
+        self.cells[x][y].count_ones()
+    }
+    pub fn mark_as_solved(&mut self, x: usize, y: usize) {
+        self.remove_candidates(x, y, CandidateWord::MAX);
+    }
+    // a solved cell's only candidate is its value, even once its candidates are cleared
+    pub(crate) fn effective_mask(
+        &self,
+        gameboard: &Gameboard<X, Y>,
+        (x, y): (usize, usize),
+    ) -> CandidateWord {
+        match gameboard.cell(x, y) {
+            0 => self.cells[x][y],
+            n => n.to_cell_mask(),
+        }
+    }
+    #[must_use]
+    pub fn narrowed_by(&self, (x, y): (usize, usize)) -> &'static str {
+        self.narrowed_by[x][y]
+    }
+    #[must_use]
+    pub fn candidates_at(&self, (x, y): (usize, usize)) -> CandidateSet {
+        CandidateSet(self.cells[x][y])
+    }
+    #[must_use]
+    pub fn bivalue_cells(&self) -> Vec<(usize, usize)> {
+        (0..X)
+            .flat_map(|x| (0..Y).map(move |y| (x, y)))
+            .filter(|(x, y)| self.remaining_candidates(*x, *y) == 2)
+            .collect()
+    }
+    #[must_use]
+    pub fn digit_positions(&self, region: &Region, digit: u8) -> Vec<(usize, usize)> {
+        region
+            .positions()
+            .iter()
+            .filter(|position| self.candidates_at(**position).contains(digit))
+            .copied()
+            .collect()
+    }
+    #[must_use]
+    pub fn is_contradicted(&self, gameboard: &Gameboard<X, Y>) -> bool {
+        !self.exhausted_cells(gameboard).is_empty()
+    }
+    // empty cells with no candidates left
+    #[must_use]
+    pub fn exhausted_cells(&self, gameboard: &Gameboard<X, Y>) -> Vec<(usize, usize)> {
+        self.empty_cells_with(gameboard, 0)
+    }
+    // empty cells down to one candidate that hasn't been placed yet
+    #[must_use]
+    pub fn pending_singles(&self, gameboard: &Gameboard<X, Y>) -> Vec<(usize, usize)> {
+        self.empty_cells_with(gameboard, 1)
+    }
+    fn empty_cells_with(&self, gameboard: &Gameboard<X, Y>, count: u32) -> Vec<(usize, usize)> {
+        (0..X)
+            .flat_map(|x| (0..Y).map(move |y| (x, y)))
+            .filter(|(x, y)| {
+                gameboard.cell(*x, *y) == 0 && self.remaining_candidates(*x, *y) == count
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,637 @@
+use std::{
+    cell::{OnceCell, RefCell},
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fmt::{Display, Error, Formatter},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    candidates::ToResult, Backend, CandidateSet, Candidates, CellOrder, Gameboard, Observer, Rule,
+    ValueOrder,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Strategy {
+    // visit every rule in turn, then place singles
+    #[default]
+    RoundRobin,
+    // visit rules cheapest first, restarting from the cheapest after any progress
+    CheapestFirst,
+    // only revisit rules whose scope was touched by the last change, cheapest first
+    PriorityQueue,
+    // like cheapest-first, but ordered by measured time per productive visit, learned
+    // over every run of the engine so far
+    Adaptive,
+}
+
+impl Strategy {
+    pub const ALL: [Self; 4] = [
+        Self::RoundRobin,
+        Self::CheapestFirst,
+        Self::PriorityQueue,
+        Self::Adaptive,
+    ];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::RoundRobin => "round-robin",
+            Self::CheapestFirst => "cheapest-first",
+            Self::PriorityQueue => "priority-queue",
+            Self::Adaptive => "adaptive",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|strategy| strategy.name() == name)
+    }
+}
+
+// shared with whoever may abort the solve, e.g. a GUI thread. clones cancel together
+#[derive(Debug, Clone, Default)]
+pub struct SolveHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl SolveHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+// built up from `SolverConfig::default()` with the methods below, so new settings
+// can be added without breaking anyone
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct SolverConfig {
+    pub strategy: Strategy,
+    // rule indices to use in place of ordering by cost, e.g. a `Stats::rule_order`
+    // learned by the adaptive strategy. ignored unless it covers every rule, and
+    // free rules are still visited first
+    pub rule_order: Vec<usize>,
+    pub backend: Backend,
+    // only used by the search backend
+    pub cell_order: CellOrder,
+    pub value_order: ValueOrder,
+    // technique to stop before, the first time it would make progress
+    pub stop_at: Option<String>,
+    // checked before every rule visit and guess, leaving the board as far as it got
+    pub cancel: Option<SolveHandle>,
+}
+
+impl SolverConfig {
+    #[must_use]
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    #[must_use]
+    pub fn rule_order(mut self, rule_order: Vec<usize>) -> Self {
+        self.rule_order = rule_order;
+        self
+    }
+
+    #[must_use]
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    #[must_use]
+    pub fn cell_order(mut self, cell_order: CellOrder) -> Self {
+        self.cell_order = cell_order;
+        self
+    }
+
+    #[must_use]
+    pub fn value_order(mut self, value_order: ValueOrder) -> Self {
+        self.value_order = value_order;
+        self
+    }
+
+    #[must_use]
+    pub fn stop_at(mut self, technique: impl Into<String>) -> Self {
+        self.stop_at = Some(technique.into());
+        self
+    }
+
+    #[must_use]
+    pub fn cancel(mut self, handle: SolveHandle) -> Self {
+        self.cancel = Some(handle);
+        self
+    }
+}
+
+// placing naked singles isn't a rule, so its explanation lives here
+pub(crate) const APPLY_UNIQUES_EXPLANATION: &str =
+    "a cell with only one candidate left takes that digit";
+
+// where a run stopped for `SolverConfig::stop_at`, with what the technique would do
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct StopPoint {
+    pub technique: &'static str,
+    pub explanation: &'static str,
+    pub eliminations: Vec<((usize, usize), CandidateSet)>,
+    pub placements: Vec<((usize, usize), u8)>,
+}
+
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RuleStats {
+    pub name: &'static str,
+    pub visits: usize,
+    pub productive_visits: usize,
+    pub elapsed: Duration,
+}
+
+impl RuleStats {
+    #[must_use]
+    pub fn average(&self) -> Duration {
+        match u32::try_from(self.visits) {
+            Ok(0) => Duration::ZERO,
+            Ok(visits) => self.elapsed / visits,
+            Err(_) => Duration::MAX,
+        }
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn hit_rate(&self) -> f64 {
+        match self.visits {
+            0 => 0.0,
+            visits => self.productive_visits as f64 / visits as f64,
+        }
+    }
+
+    // time spent per productive visit. unvisited rules come first so they get
+    // measured, and rules that never made progress come last
+    fn score(&self) -> u128 {
+        match (self.visits, self.productive_visits) {
+            (0, _) => 0,
+            (_, 0) => u128::MAX,
+            (_, hits) => self.elapsed.as_nanos() / hits as u128,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct Stats {
+    pub strategy: Strategy,
+    pub visits: usize,
+    pub productive_visits: usize,
+    pub placements: usize,
+    // guesses tried by the search backend
+    pub nodes: usize,
+    // the guesses left standing in the solution, so fewer than `nodes` when some
+    // were abandoned. 0 means the rules managed alone
+    pub guesses: usize,
+    pub elapsed: Duration,
+    // indexed like the engine's rules
+    pub rules: Vec<RuleStats>,
+    // rule indices by time per productive visit over every run so far
+    pub rule_order: Vec<usize>,
+    pub stopped: Option<StopPoint>,
+    // the solve was aborted through its `SolveHandle`
+    pub cancelled: bool,
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        writeln!(f, "strategy: {}", self.strategy.name())?;
+        writeln!(f, "rule visits: {}", self.visits)?;
+        writeln!(f, "productive visits: {}", self.productive_visits)?;
+        writeln!(f, "placements: {}", self.placements)?;
+        writeln!(f, "nodes explored: {}", self.nodes)?;
+        writeln!(f, "guesses needed: {}", self.guesses)?;
+        writeln!(f, "elapsed: {:?}", self.elapsed)?;
+
+        let order: Vec<String> = self.rule_order.iter().map(usize::to_string).collect();
+        writeln!(f, "rule order: {}", order.join(","))?;
+
+        if self.cancelled {
+            writeln!(f, "cancelled")?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Engine<const X: usize, const Y: usize> {
+    rules: Vec<Box<dyn Rule<X, Y>>>,
+    pub(crate) config: SolverConfig,
+    // rule indices sorted by cost
+    by_cost: Vec<usize>,
+    // summed over every run, for the adaptive strategy
+    learned: RefCell<Vec<RuleStats>>,
+    // rows, columns and boxes for the search backend, built by its first search
+    pub(crate) regions: OnceCell<Vec<Vec<(usize, usize)>>>,
+}
+
+impl<const X: usize, const Y: usize> Engine<X, Y> {
+    #[must_use]
+    pub fn new(rules: Vec<Box<dyn Rule<X, Y>>>, config: SolverConfig) -> Self {
+        let mut by_cost: Vec<usize> = (0..rules.len()).collect();
+        by_cost.sort_by_key(|i| rules[*i].cost());
+
+        let mut frozen = config.rule_order.clone();
+        frozen.sort_unstable();
+
+        if frozen == (0..rules.len()).collect::<Vec<_>>() {
+            by_cost.clone_from(&config.rule_order);
+            by_cost.sort_by_key(|i| rules[*i].cost() > 0);
+        }
+
+        let learned = RefCell::new(Self::empty_stats(&rules));
+
+        Self {
+            rules,
+            config,
+            by_cost,
+            learned,
+            regions: OnceCell::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn rules(&self) -> &[Box<dyn Rule<X, Y>>] {
+        &self.rules
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.config
+            .cancel
+            .as_ref()
+            .is_some_and(SolveHandle::is_cancelled)
+    }
+
+    pub fn run(
+        &self,
+        gameboard: &mut Gameboard<X, Y>,
+        candidates: &mut Candidates<X, Y>,
+        mut observer: Option<&mut dyn Observer<X, Y>>,
+    ) -> Stats {
+        let started = Instant::now();
+
+        let mut stats = Stats {
+            strategy: self.config.strategy,
+            rules: Self::empty_stats(&self.rules),
+            ..Stats::default()
+        };
+
+        match self.config.strategy {
+            Strategy::RoundRobin => {
+                self.run_round_robin(gameboard, candidates, &mut observer, &mut stats);
+            }
+            Strategy::CheapestFirst => {
+                self.run_cheapest_first(gameboard, candidates, &mut observer, &mut stats);
+            }
+            Strategy::PriorityQueue => {
+                self.run_priority_queue(gameboard, candidates, &mut observer, &mut stats);
+            }
+            Strategy::Adaptive => {
+                self.run_adaptive(gameboard, candidates, &mut observer, &mut stats);
+            }
+        }
+
+        let mut learned = self.learned.borrow_mut();
+
+        for (total, run) in learned.iter_mut().zip(&stats.rules) {
+            total.visits += run.visits;
+            total.productive_visits += run.productive_visits;
+            total.elapsed += run.elapsed;
+        }
+
+        stats.rule_order = self.order_by_score(&learned, &stats.rules);
+        stats.elapsed = started.elapsed();
+
+        stats
+    }
+
+    fn empty_stats(rules: &[Box<dyn Rule<X, Y>>]) -> Vec<RuleStats> {
+        rules
+            .iter()
+            .map(|rule| RuleStats {
+                name: rule.name(),
+                ..RuleStats::default()
+            })
+            .collect()
+    }
+
+    // earlier runs plus the current one, if it isn't in `learned` yet. free rules
+    // stay first whatever they score, since the others rely on their bookkeeping
+    fn order_by_score(&self, learned: &[RuleStats], current: &[RuleStats]) -> Vec<usize> {
+        let combined: Vec<RuleStats> = learned
+            .iter()
+            .zip(current)
+            .map(|(total, run)| RuleStats {
+                name: total.name,
+                visits: total.visits + run.visits,
+                productive_visits: total.productive_visits + run.productive_visits,
+                elapsed: total.elapsed + run.elapsed,
+            })
+            .collect();
+
+        let mut order: Vec<usize> = (0..combined.len()).collect();
+        order.sort_by_key(|i| (self.rules[*i].cost() > 0, combined[*i].score()));
+
+        order
+    }
+
+    fn visit(
+        &self,
+        i: usize,
+        gameboard: &Gameboard<X, Y>,
+        candidates: &mut Candidates<X, Y>,
+        observer: &mut Option<&mut dyn Observer<X, Y>>,
+        stats: &mut Stats,
+    ) -> bool {
+        let rule = self.rules[i].as_ref();
+
+        stats.cancelled |= self.is_cancelled();
+
+        if stats.stopped.is_some() || stats.cancelled {
+            return false;
+        }
+
+        if self.config.stop_at.as_deref() == Some(rule.name()) {
+            let mut trial = candidates.clone();
+            rule.visit(gameboard, &mut trial);
+
+            let eliminations: Vec<((usize, usize), CandidateSet)> = (0..X)
+                .flat_map(|x| (0..Y).map(move |y| (x, y)))
+                .filter_map(|(x, y)| {
+                    let removed = candidates.cells[x][y] & !trial.cells[x][y];
+                    (removed != 0).then_some(((x, y), CandidateSet(removed)))
+                })
+                .collect();
+
+            if !eliminations.is_empty() {
+                stats.stopped = Some(StopPoint {
+                    technique: rule.name(),
+                    explanation: rule.explanation(),
+                    eliminations,
+                    placements: vec![],
+                });
+                return false;
+            }
+        }
+
+        let before = candidates.cells;
+
+        candidates.set_cause(rule.name());
+
+        let started = Instant::now();
+        rule.visit(gameboard, candidates);
+        stats.rules[i].elapsed += started.elapsed();
+
+        stats.visits += 1;
+        stats.rules[i].visits += 1;
+
+        if candidates.cells == before {
+            return false;
+        }
+
+        stats.productive_visits += 1;
+        stats.rules[i].productive_visits += 1;
+
+        if let Some(observer) = observer.as_deref_mut() {
+            observer.observe(rule.name(), &before, gameboard, candidates);
+        }
+
+        true
+    }
+
+    fn place(
+        &self,
+        gameboard: &mut Gameboard<X, Y>,
+        candidates: &mut Candidates<X, Y>,
+        observer: &mut Option<&mut dyn Observer<X, Y>>,
+        stats: &mut Stats,
+    ) -> bool {
+        stats.cancelled |= self.is_cancelled();
+
+        if stats.stopped.is_some() || stats.cancelled {
+            return false;
+        }
+
+        if self.config.stop_at.as_deref() == Some("apply_uniques") {
+            let placements: Vec<((usize, usize), u8)> = (0..X)
+                .flat_map(|x| (0..Y).map(move |y| (x, y)))
+                .filter(|(x, y)| candidates.remaining_candidates(*x, *y) == 1)
+                .map(|(x, y)| ((x, y), candidates.cells[x][y].to_result()))
+                .collect();
+
+            if !placements.is_empty() {
+                stats.stopped = Some(StopPoint {
+                    technique: "apply_uniques",
+                    explanation: APPLY_UNIQUES_EXPLANATION,
+                    eliminations: vec![],
+                    placements,
+                });
+                return false;
+            }
+        }
+
+        gameboard.clear_newly_solved();
+
+        if !candidates.apply_uniques(gameboard) {
+            return false;
+        }
+
+        stats.placements += gameboard.newly_solved.len();
+
+        if let Some(observer) = observer.as_deref_mut() {
+            observer.observe("apply_uniques", &candidates.cells, gameboard, candidates);
+        }
+
+        true
+    }
+
+    fn run_round_robin(
+        &self,
+        gameboard: &mut Gameboard<X, Y>,
+        candidates: &mut Candidates<X, Y>,
+        observer: &mut Option<&mut dyn Observer<X, Y>>,
+        stats: &mut Stats,
+    ) {
+        loop {
+            let mut progress = false;
+
+            for i in 0..self.rules.len() {
+                progress |= self.visit(i, gameboard, candidates, observer, stats);
+            }
+
+            // a pass that only eliminated can still open up the next one
+            if !self.place(gameboard, candidates, observer, stats) && !progress {
+                break;
+            }
+        }
+    }
+
+    fn run_cheapest_first(
+        &self,
+        gameboard: &mut Gameboard<X, Y>,
+        candidates: &mut Candidates<X, Y>,
+        observer: &mut Option<&mut dyn Observer<X, Y>>,
+        stats: &mut Stats,
+    ) {
+        'restart: loop {
+            for i in &self.by_cost {
+                if self.visit(*i, gameboard, candidates, observer, stats) {
+                    self.place(gameboard, candidates, observer, stats);
+                    continue 'restart;
+                }
+            }
+
+            if !self.place(gameboard, candidates, observer, stats) {
+                break;
+            }
+        }
+    }
+
+    fn run_priority_queue(
+        &self,
+        gameboard: &mut Gameboard<X, Y>,
+        candidates: &mut Candidates<X, Y>,
+        observer: &mut Option<&mut dyn Observer<X, Y>>,
+        stats: &mut Stats,
+    ) {
+        let mut queue: BinaryHeap<Reverse<(u32, usize)>> = BinaryHeap::new();
+        let mut queued = vec![true; self.rules.len()];
+
+        for (i, rule) in self.rules.iter().enumerate() {
+            queue.push(Reverse((rule.cost(), i)));
+        }
+
+        loop {
+            while let Some(Reverse((_, i))) = queue.pop() {
+                queued[i] = false;
+
+                let before = candidates.cells;
+
+                if !self.visit(i, gameboard, candidates, observer, stats) {
+                    continue;
+                }
+
+                self.place(gameboard, candidates, observer, stats);
+
+                let mut changed: Vec<(usize, usize)> = (0..X)
+                    .flat_map(|x| (0..Y).map(move |y| (x, y)))
+                    .filter(|(x, y)| candidates.cells[*x][*y] != before[*x][*y])
+                    .collect();
+                changed.extend(gameboard.newly_solved.iter().copied());
+
+                self.requeue(&changed, &mut queue, &mut queued);
+            }
+
+            // anything left over from the last pass without a rule making progress
+            if !self.place(gameboard, candidates, observer, stats) {
+                break;
+            }
+
+            let changed = gameboard.newly_solved.clone();
+            self.requeue(&changed, &mut queue, &mut queued);
+        }
+    }
+
+    fn run_adaptive(
+        &self,
+        gameboard: &mut Gameboard<X, Y>,
+        candidates: &mut Candidates<X, Y>,
+        observer: &mut Option<&mut dyn Observer<X, Y>>,
+        stats: &mut Stats,
+    ) {
+        'restart: loop {
+            let order = self.order_by_score(&self.learned.borrow(), &stats.rules);
+
+            for i in order {
+                if self.visit(i, gameboard, candidates, observer, stats) {
+                    self.place(gameboard, candidates, observer, stats);
+                    continue 'restart;
+                }
+            }
+
+            if !self.place(gameboard, candidates, observer, stats) {
+                break;
+            }
+        }
+    }
+
+    fn requeue(
+        &self,
+        changed: &[(usize, usize)],
+        queue: &mut BinaryHeap<Reverse<(u32, usize)>>,
+        queued: &mut [bool],
+    ) {
+        for (i, rule) in self.rules.iter().enumerate() {
+            if queued[i] {
+                continue;
+            }
+
+            let touched = match rule.scope() {
+                None => true,
+                Some(scope) => scope.iter().any(|position| changed.contains(position)),
+            };
+
+            if touched {
+                queue.push(Reverse((rule.cost(), i)));
+                queued[i] = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{io, Solver, SolverConfig, Strategy};
+
+    #[test]
+    fn every_strategy_finishes_what_the_rules_can() {
+        // stalls after three placements if a pass that only eliminates ends the run
+        let mut puzzle = io::parse_ss(
+            ".....541...18......95....62.7...49......7....3..6.....1.84..7.5.64.5..31....1....",
+        )
+        .unwrap();
+        puzzle.state[2][5] = 1;
+
+        for strategy in Strategy::ALL {
+            let mut gameboard = puzzle.clone();
+            Solver::new(SolverConfig::default().strategy(strategy), &[])
+                .solve(&mut gameboard, None);
+
+            assert!(gameboard.is_solved(), "{}", strategy.name());
+        }
+    }
+
+    #[test]
+    fn stats_print_the_strategy_by_name() {
+        let mut gameboard = io::parse_ss(
+            ".....541...18......95....62.7...49......7....3..6.....1.84..7.5.64.5..31....1....",
+        )
+        .unwrap();
+        let (_, stats) = Solver::new(
+            SolverConfig::default().strategy(Strategy::PriorityQueue),
+            &[],
+        )
+        .solve(&mut gameboard, None);
+
+        assert!(stats.to_string().starts_with("strategy: priority-queue\n"));
+    }
+}
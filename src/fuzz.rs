@@ -0,0 +1,387 @@
+use std::fmt::{Display, Error, Formatter};
+
+use crate::{
+    build_9x9_regions, build_9x9_rules, candidates::ToCellMask, is_valid_solution,
+    rules::rules_for_regions, Backend, Candidates, Engine, Gameboard, Rule, Solver, SolverConfig,
+    Variant,
+};
+
+// xorshift64, enough to shuffle boards reproducibly from a seed
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // xorshift never leaves zero
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct FuzzFailure {
+    pub round: usize,
+    pub puzzle: Gameboard<9, 9>,
+    pub solution: Gameboard<9, 9>,
+    pub position: (usize, usize),
+}
+
+impl Display for FuzzFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let (x, y) = self.position;
+
+        write!(
+            f,
+            "round {}: r{}c{} lost its solution digit {}",
+            self.round,
+            x + 1,
+            y + 1,
+            self.solution.cell(x, y)
+        )
+    }
+}
+
+impl std::error::Error for FuzzFailure {}
+
+/// Shuffles `solution` with digit relabelling, band, stack, row and column swaps and
+/// transposition, removes a random number of clues, and runs the rules on the result,
+/// `rounds` times. The rules only make sound deductions, so every placement must match
+/// the shuffled solution and every empty cell must keep its solution digit as a
+/// candidate. Stalling short of a full solve is fine.
+///
+/// # Errors
+///
+/// Returns the first puzzle whose solution the rules ruled out.
+pub fn fuzz_solve(
+    solution: &Gameboard<9, 9>,
+    config: SolverConfig,
+    rounds: usize,
+    seed: u64,
+) -> Result<(), Box<FuzzFailure>> {
+    let engine = Engine::new(build_9x9_rules(), config);
+    let mut rng = Rng::new(seed);
+
+    for round in 0..rounds {
+        let solution = transform(solution, &mut rng);
+
+        let mut state = solution.state;
+        let clues_removed = 30 + rng.below(31);
+
+        let mut cells: Vec<(usize, usize)> =
+            (0..9).flat_map(|x| (0..9).map(move |y| (x, y))).collect();
+        rng.shuffle(&mut cells);
+
+        for (x, y) in &cells[..clues_removed] {
+            state[*x][*y] = 0;
+        }
+
+        let puzzle = Gameboard::from(state);
+
+        let mut gameboard = puzzle.clone();
+        let mut candidates = Candidates::default();
+        engine.run(&mut gameboard, &mut candidates, None);
+
+        let lost = cells.iter().find(|(x, y)| {
+            let expected = solution.cell(*x, *y);
+
+            match gameboard.cell(*x, *y) {
+                0 => candidates.cells[*x][*y] & expected.to_cell_mask() == 0,
+                n => n != expected,
+            }
+        });
+
+        if let Some(position) = lost {
+            return Err(Box::new(FuzzFailure {
+                round,
+                puzzle,
+                solution,
+                position: *position,
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+// bands and stacks are shuffled, then the rows in each band and columns in each stack
+fn shuffled_lines(rng: &mut Rng) -> Vec<usize> {
+    let mut bands = [0, 1, 2];
+    rng.shuffle(&mut bands);
+
+    bands
+        .iter()
+        .flat_map(|band| {
+            let mut lines = [band * 3, band * 3 + 1, band * 3 + 2];
+            rng.shuffle(&mut lines);
+            lines
+        })
+        .collect()
+}
+
+fn transform(solution: &Gameboard<9, 9>, rng: &mut Rng) -> Gameboard<9, 9> {
+    let mut digits = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+    rng.shuffle(&mut digits);
+
+    let rows = shuffled_lines(rng);
+    let columns = shuffled_lines(rng);
+    let transpose = rng.below(2) == 1;
+
+    let mut state = [[0; 9]; 9];
+
+    for (x, row) in rows.iter().enumerate() {
+        for (y, column) in columns.iter().enumerate() {
+            let (x, y) = if transpose { (y, x) } else { (x, y) };
+
+            state[x][y] = digits[usize::from(solution.cell(*row, *column)) - 1];
+        }
+    }
+
+    state.into()
+}
+
+// how the rules alone and a plain search disagreed about a puzzle
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Divergence {
+    NoSolution,
+    MultipleSolutions,
+    // the rules placed a different digit here or ruled the solution's out
+    LostSolution((usize, usize)),
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Self::NoSolution => write!(f, "search found no solution"),
+            Self::MultipleSolutions => write!(f, "search found more than one solution"),
+            Self::LostSolution((x, y)) => {
+                write!(f, "the rules lost the solution at r{}c{}", x + 1, y + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+// up to `limit` solutions, from trying every digit in the cell with the fewest that
+// keep every rule satisfied. nothing here reads or narrows candidates. `watching`
+// holds, for each cell, the rules whose scope covers it or that read the whole board
+fn backtrack(
+    gameboard: &mut Gameboard<9, 9>,
+    rules: &[Box<dyn Rule<9, 9>>],
+    watching: &[[Vec<usize>; 9]; 9],
+    limit: usize,
+    solutions: &mut Vec<Gameboard<9, 9>>,
+) {
+    let fits = |gameboard: &mut Gameboard<9, 9>, (x, y): (usize, usize), n: u8| {
+        gameboard.state[x][y] = n;
+        let fits = watching[x][y]
+            .iter()
+            .all(|rule| rules[*rule].is_satisfied(gameboard));
+        gameboard.state[x][y] = 0;
+        fits
+    };
+
+    let mut best: Option<((usize, usize), Vec<u8>)> = None;
+
+    for (x, y) in (0..9).flat_map(|x| (0..9).map(move |y| (x, y))) {
+        if gameboard.cell(x, y) != 0 {
+            continue;
+        }
+
+        let digits: Vec<u8> = (1..=9).filter(|n| fits(gameboard, (x, y), *n)).collect();
+
+        if best
+            .as_ref()
+            .is_none_or(|(_, fewest)| digits.len() < fewest.len())
+        {
+            let dead_end = digits.is_empty();
+            best = Some(((x, y), digits));
+
+            if dead_end {
+                break;
+            }
+        }
+    }
+
+    let Some(((x, y), digits)) = best else {
+        if is_valid_solution(gameboard, rules) {
+            solutions.push(gameboard.clone());
+        }
+        return;
+    };
+
+    for n in digits {
+        gameboard.state[x][y] = n;
+        backtrack(gameboard, rules, watching, limit, solutions);
+        gameboard.state[x][y] = 0;
+
+        if solutions.len() >= limit {
+            return;
+        }
+    }
+}
+
+/// Solves `puzzle` with the rules alone and with a plain backtracking search that
+/// only asks the classic and variant rules whether a board satisfies them, and checks
+/// they agree. The search has to find exactly one solution, and every cell the rules
+/// fill or leave a candidate in has to keep that solution's digit. Stalling short of
+/// a full solve isn't a divergence.
+///
+/// # Errors
+///
+/// Returns how the two disagreed.
+pub fn cross_check(
+    puzzle: &Gameboard<9, 9>,
+    config: SolverConfig,
+    variants: &[Variant],
+) -> Result<(), Divergence> {
+    // the reference never runs a rule's deductions, so a bug in one can't hide in both
+    let mut rules = rules_for_regions(&build_9x9_regions());
+    rules.extend(variants.iter().flat_map(Variant::to_rules));
+
+    let watching = std::array::from_fn(|x| {
+        std::array::from_fn(|y| {
+            (0..rules.len())
+                .filter(|rule| {
+                    rules[*rule]
+                        .scope()
+                        .is_none_or(|scope| scope.contains(&(x, y)))
+                })
+                .collect()
+        })
+    });
+
+    let mut solutions = vec![];
+    backtrack(&mut puzzle.clone(), &rules, &watching, 2, &mut solutions);
+
+    let solution = match solutions.as_slice() {
+        [] => return Err(Divergence::NoSolution),
+        [solution] => solution.clone(),
+        _ => return Err(Divergence::MultipleSolutions),
+    };
+
+    let mut gameboard = puzzle.clone();
+
+    let logic = SolverConfig {
+        backend: Backend::Logic,
+        stop_at: None,
+        ..config
+    };
+    let (candidates, _) = Solver::new(logic, variants).solve(&mut gameboard, None);
+
+    let lost = (0..9)
+        .flat_map(|x| (0..9).map(move |y| (x, y)))
+        .find(|(x, y)| {
+            let expected = solution.cell(*x, *y);
+
+            match gameboard.cell(*x, *y) {
+                0 => candidates.cells[*x][*y] & expected.to_cell_mask() == 0,
+                n => n != expected,
+            }
+        });
+
+    match lost {
+        Some(position) => Err(Divergence::LostSolution(position)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{io, Strategy};
+
+    const XV_PUZZLE: &str = "\
+xv x r1c1 r1c2
+xv x r1c4 r1c5
+xv x r1c6 r1c7
+xv x r1c6 r2c6
+xv x r2c1 r3c1
+xv x r2c2 r3c2
+xv x r2c4 r2c5
+xv x r3c4 r4c4
+xv v r3c6 r4c6
+xv v r4c2 r4c3
+xv x r4c4 r4c5
+xv x r4c6 r4c7
+xv x r4c6 r5c6
+xv v r5c2 r5c3
+xv x r5c2 r6c2
+xv x r5c4 r5c5
+xv v r5c5 r6c5
+xv x r5c8 r6c8
+xv v r5c9 r6c9
+xv x r6c4 r6c5
+xv v r6c7 r6c8
+xv x r7c2 r7c3
+xv x r7c4 r7c5
+xv x r7c6 r8c6
+xv x r7c8 r7c9
+xv x r8c1 r9c1
+xv v r8c4 r8c5
+xv x r8c7 r8c8
+xv v r8c8 r9c8
+xv x r9c2 r9c3
+xv x r9c6 r9c7
+xv x r9c8 r9c9
+xv negative
+1...8...5...6...8...4...2...3...4...5...3...2...8...1...9...5...5...2...4...6...8
+";
+
+    const SOLUTION: &str =
+        "196287345725643981384951276832194657541736892967825413219378564658412739473569128";
+
+    #[test]
+    fn rules_keep_the_solution_for_every_strategy() {
+        let solution = io::parse_ss(SOLUTION).unwrap();
+
+        for strategy in Strategy::ALL {
+            let config = SolverConfig::default().strategy(strategy);
+
+            if let Err(failure) = fuzz_solve(&solution, config, 25, 7) {
+                panic!("{}: {failure}", strategy.name());
+            }
+        }
+    }
+
+    #[test]
+    fn cross_check_agrees_on_known_puzzles() {
+        let puzzles = [
+            ".....541...18......95....62.7...49......7....3..6.....1.84..7.5.64.5..31....1....",
+            "..97...32.5.2...4......9........8....4.59..1..8...1..5..3....6.6..98......7.2....",
+            "....5..398...2..4.9...782..2.9...4.......7..6.4......81..........73.6....86.....7",
+        ];
+
+        for puzzle in puzzles {
+            let gameboard = io::parse_ss(puzzle).unwrap();
+            assert_eq!(
+                cross_check(&gameboard, SolverConfig::default(), &[]),
+                Ok(())
+            );
+        }
+
+        let xv = io::parse_extended(XV_PUZZLE).unwrap();
+        assert_eq!(
+            cross_check(&xv.gameboard, SolverConfig::default(), &xv.variants),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,429 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Error, Formatter},
+};
+
+use crate::{candidates::ToCellMask, rules::build_9x9_regions};
+
+// `X` rows of `Y` cells. everything indexing the board, `state`, the candidates and
+// positions alike, takes the row first, so `(x, y)` is row x, column y and is written
+// `r{x+1}c{y+1}`
+#[derive(Debug, Clone)]
+pub struct Gameboard<const X: usize, const Y: usize> {
+    pub(crate) state: [[u8; Y]; X],
+    pub(crate) newly_solved: Vec<(usize, usize)>,
+    // cells an editor won't let the player change, usually the givens
+    pub(crate) locked: [[bool; Y]; X],
+}
+
+// `set_cell` was asked to change a locked cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockedCell(pub (usize, usize));
+
+impl Display for LockedCell {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let (x, y) = self.0;
+        write!(f, "r{}c{} is locked", x + 1, y + 1)
+    }
+}
+
+impl std::error::Error for LockedCell {}
+
+impl<const X: usize, const Y: usize> Gameboard<X, Y> {
+    // 0 for an empty cell
+    #[must_use]
+    pub fn cell(&self, row: usize, column: usize) -> u8 {
+        self.state[row][column]
+    }
+
+    #[must_use]
+    pub fn row(&self, row: usize) -> &[u8; Y] {
+        &self.state[row]
+    }
+
+    #[must_use]
+    pub fn col(&self, column: usize) -> [u8; X] {
+        std::array::from_fn(|row| self.state[row][column])
+    }
+
+    /// Sets a cell, or clears it with 0, unless it's locked.
+    ///
+    /// # Errors
+    ///
+    /// Fails without changing anything if the cell is locked.
+    pub fn set_cell(&mut self, row: usize, column: usize, value: u8) -> Result<(), LockedCell> {
+        if self.locked[row][column] {
+            return Err(LockedCell((row, column)));
+        }
+
+        self.force_cell(row, column, value);
+
+        Ok(())
+    }
+
+    // sets a cell whether or not it's locked, leaving the lock as it was. clearing a
+    // cell doesn't solve it, so only digits are passed on to the rules
+    pub fn force_cell(&mut self, row: usize, column: usize, value: u8) {
+        self.state[row][column] = value;
+
+        if value != 0 {
+            self.newly_solved.push((row, column));
+        }
+    }
+
+    #[must_use]
+    pub fn is_locked(&self, row: usize, column: usize) -> bool {
+        self.locked[row][column]
+    }
+
+    pub fn set_locked(&mut self, row: usize, column: usize, locked: bool) {
+        self.locked[row][column] = locked;
+    }
+
+    // locks every filled cell and unlocks the rest, for starting to play a puzzle
+    pub fn lock_givens(&mut self) {
+        for x in 0..X {
+            for y in 0..Y {
+                self.locked[x][y] = self.state[x][y] != 0;
+            }
+        }
+    }
+
+    pub(crate) fn clear_newly_solved(&mut self) {
+        self.newly_solved.clear();
+    }
+
+    // cells that differ from `other`, with this board's digit then the other's
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> Vec<((usize, usize), u8, u8)> {
+        (0..X)
+            .flat_map(|x| (0..Y).map(move |y| (x, y)))
+            .filter(|(x, y)| self.cell(*x, *y) != other.cell(*x, *y))
+            .map(|(x, y)| ((x, y), self.cell(x, y), other.cell(x, y)))
+            .collect()
+    }
+
+    // FNV-1a over the size and cells, so ids are stable across runs, platforms and
+    // versions. it hashes the grid as given: transformed copies of a puzzle get
+    // different ids, and the id of `canonical()`'s board is the one they share
+    #[must_use]
+    pub fn fingerprint(&self) -> u64 {
+        #[allow(clippy::cast_possible_truncation)]
+        let header = [X as u8, Y as u8];
+
+        fnv1a(
+            FNV_OFFSET_BASIS,
+            header.iter().chain(self.state.iter().flatten()),
+        )
+    }
+}
+
+pub(crate) const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+// continues a hash from `hash`, so other ids can build on a board's
+pub(crate) fn fnv1a<'a>(hash: u64, bytes: impl IntoIterator<Item = &'a u8>) -> u64 {
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    bytes.into_iter().fold(hash, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+impl Gameboard<9, 9> {
+    #[must_use]
+    pub fn is_solved(&self) -> bool {
+        build_9x9_regions().iter().all(|region| {
+            region
+                .iter()
+                .map(|(x, y)| self.cell(*x, *y))
+                .filter(|n| *n != 0)
+                .fold(0, |mask, n| mask | n.to_cell_mask())
+                == 511
+        })
+    }
+
+    // cells whose digit is repeated elsewhere in their row, column or box
+    #[must_use]
+    pub fn conflicts(&self) -> Vec<(usize, usize)> {
+        let regions = build_9x9_regions();
+
+        (0..9)
+            .flat_map(|x| (0..9).map(move |y| (x, y)))
+            .filter(|(x, y)| {
+                let n = self.cell(*x, *y);
+
+                n != 0
+                    && regions
+                        .iter()
+                        .filter(|region| region.contains(&(*x, *y)))
+                        .flatten()
+                        .any(|(x2, y2)| (*x2, *y2) != (*x, *y) && self.cell(*x2, *y2) == n)
+            })
+            .collect()
+    }
+
+    // how many of each digit are still to be placed, indexed from digit 1. a digit
+    // placed too often, which only a contradicted board has, counts 0
+    #[must_use]
+    pub fn remaining_digits(&self) -> [usize; 9] {
+        let mut remaining = [9_usize; 9];
+
+        for n in self.state.iter().flatten().filter(|n| **n != 0) {
+            let count = &mut remaining[usize::from(*n) - 1];
+            *count = count.saturating_sub(1);
+        }
+
+        remaining
+    }
+
+    // the least grid, read row by row with blanks as 0, of every copy of the board
+    // that transposing, reordering the bands, the stacks and the rows and columns
+    // within them, and relabelling the digits can make. a puzzle and its disguised
+    // copies share it
+    #[must_use]
+    pub fn canonical(&self) -> Self {
+        let transposed = std::array::from_fn(|x| self.col(x));
+        let mut least = [[u8::MAX; 9]; 9];
+
+        for grid in [self.state, transposed] {
+            for columns in column_orders() {
+                let grid = grid.map(|row| columns.map(|y| row[y]));
+                least_rows(&grid, &Rows::default(), &mut least);
+            }
+        }
+
+        Self::from(least)
+    }
+}
+
+const ORDERS_OF_THREE: [[usize; 3]; 6] = [
+    [0, 1, 2],
+    [0, 2, 1],
+    [1, 0, 2],
+    [1, 2, 0],
+    [2, 0, 1],
+    [2, 1, 0],
+];
+
+// every way to reorder the stacks and the columns within each
+fn column_orders() -> Vec<[usize; 9]> {
+    let mut orders = vec![];
+
+    for stacks in ORDERS_OF_THREE {
+        for first in ORDERS_OF_THREE {
+            for second in ORDERS_OF_THREE {
+                for third in ORDERS_OF_THREE {
+                    let within = [first, second, third];
+                    orders.push(std::array::from_fn(|i| {
+                        stacks[i / 3] * 3 + within[i / 3][i % 3]
+                    }));
+                }
+            }
+        }
+    }
+
+    orders
+}
+
+// the rows placed so far by `least_rows`, and the labels their digits got in order of
+// first appearance
+#[derive(Clone, Copy, Default)]
+struct Rows {
+    placed: usize,
+    used: [bool; 9],
+    band: usize,
+    labels: [u8; 10],
+    next_label: u8,
+}
+
+// tries each row that can go next, dropping any that reads greater than the least grid's
+// row at that depth. `least` matches the rows placed so far on entry, and a smaller row
+// clears the rows after it, so the first completion below it takes its place
+fn least_rows(grid: &[[u8; 9]; 9], rows: &Rows, least: &mut [[u8; 9]; 9]) {
+    let depth = rows.placed;
+
+    if depth == 9 {
+        return;
+    }
+
+    // a band is finished before the next one is started
+    let choices =
+        (0..9).filter(|x| !rows.used[*x] && (depth.is_multiple_of(3) || x / 3 == rows.band));
+
+    for x in choices {
+        let mut next = *rows;
+        let row = grid[x].map(|n| match n {
+            0 => 0,
+            n => {
+                let label = &mut next.labels[usize::from(n)];
+
+                if *label == 0 {
+                    next.next_label += 1;
+                    *label = next.next_label;
+                }
+
+                *label
+            }
+        });
+
+        match row.cmp(&least[depth]) {
+            Ordering::Greater => continue,
+            Ordering::Less => {
+                least[depth] = row;
+                least[depth + 1..].fill([u8::MAX; 9]);
+            }
+            Ordering::Equal => {}
+        }
+
+        next.placed += 1;
+        next.used[x] = true;
+        next.band = x / 3;
+        least_rows(grid, &next, least);
+    }
+}
+
+impl<const X: usize, const Y: usize> Display for Gameboard<X, Y> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        for x in 0..X {
+            for cell in self.row(x) {
+                write!(
+                    f,
+                    "{} ",
+                    match cell {
+                        0 => ".".to_string(),
+                        a => format!("{a}"),
+                    },
+                )?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<const X: usize, const Y: usize> From<[[u8; Y]; X]> for Gameboard<X, Y> {
+    fn from(value: [[u8; Y]; X]) -> Self {
+        // givens count as solved on the first pass
+        let newly_solved = value
+            .iter()
+            .enumerate()
+            .flat_map(|(x, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, cell)| **cell != 0)
+                    .map(move |(y, _)| (x, y))
+            })
+            .collect();
+
+        Self {
+            state: value,
+            newly_solved,
+            locked: [[false; Y]; X],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Candidates;
+
+    // 6 rows of 9. the first column holds each row's number and the other cells their
+    // column's, except for the last cell, which is empty
+    fn board() -> Gameboard<6, 9> {
+        let mut state = [[0; 9]; 6];
+
+        for (x, row) in state.iter_mut().enumerate() {
+            for (y, cell) in row.iter_mut().enumerate() {
+                *cell = u8::try_from(if y == 0 { x + 1 } else { y + 1 }).unwrap();
+            }
+        }
+
+        state[5][8] = 0;
+        state.into()
+    }
+
+    #[test]
+    fn indexes_rows_first() {
+        let gameboard = board();
+
+        assert_eq!(gameboard.cell(0, 8), 9);
+        assert_eq!(gameboard.cell(5, 0), 6);
+        assert_eq!(gameboard.cell(5, 8), 0);
+        assert_eq!(gameboard.row(4), &[5, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(gameboard.col(0), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(gameboard.col(8), [9, 9, 9, 9, 9, 0]);
+    }
+
+    #[test]
+    fn construction_tracks_givens_by_row_and_column() {
+        let gameboard = board();
+
+        assert_eq!(gameboard.newly_solved.len(), 6 * 9 - 1);
+        assert!(!gameboard.newly_solved.contains(&(5, 8)));
+    }
+
+    #[test]
+    fn displays_a_line_per_row() {
+        let display = board().to_string();
+        let lines: Vec<&str> = display.lines().collect();
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "1 2 3 4 5 6 7 8 9 ");
+        assert_eq!(lines[5], "6 2 3 4 5 6 7 8 . ");
+    }
+
+    #[test]
+    fn sets_cells_by_row_and_column() {
+        let mut gameboard = board();
+
+        gameboard.set_locked(5, 7, true);
+
+        assert_eq!(gameboard.set_cell(5, 8, 4), Ok(()));
+        assert_eq!(gameboard.cell(5, 8), 4);
+        assert_eq!(gameboard.set_cell(5, 7, 1), Err(LockedCell((5, 7))));
+        assert_eq!(gameboard.cell(5, 7), 8);
+    }
+
+    #[test]
+    fn candidates_index_rows_first() {
+        let mut candidates = Candidates::<6, 9>::default();
+
+        candidates.exclude_candidate(5, 8, 3);
+
+        assert_eq!(candidates.candidates_at((5, 8)).len(), 8);
+        assert!(!candidates.candidates_at((5, 8)).contains(3));
+        assert_eq!(candidates.candidates_at((0, 0)).len(), 9);
+    }
+
+    #[test]
+    fn canonical_form_sees_through_disguises() {
+        let puzzle = crate::io::parse_ss(
+            ".....541...18......95....62.7...49......7....3..6.....1.84..7.5.64.5..31....1....",
+        )
+        .unwrap();
+
+        // transposed, with the bands, two rows, two columns and the digits shuffled
+        let mut state: [[u8; 9]; 9] = std::array::from_fn(|x| puzzle.col(x));
+        state.swap(0, 1);
+        state.rotate_left(3);
+        for row in &mut state {
+            row.swap(3, 5);
+            for n in row.iter_mut().filter(|n| **n != 0) {
+                *n = 10 - *n;
+            }
+        }
+        let disguised = Gameboard::from(state);
+
+        let canonical = puzzle.canonical();
+        assert_eq!(disguised.canonical().state, canonical.state);
+        assert_eq!(canonical.canonical().state, canonical.state);
+        assert_eq!(canonical.row(0), &[0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+        // one more clue makes it a different puzzle
+        let mut state = puzzle.state;
+        state[2][5] = 1;
+        assert_ne!(Gameboard::from(state).canonical().state, canonical.state);
+    }
+}
@@ -0,0 +1,1465 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Error, Formatter, Write},
+};
+
+use crate::{
+    gameboard::fnv1a, CandidateSet, CandidateWord, Candidates, Gameboard, Rating, Rule, Variant,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseError {
+    InvalidCharacter(char),
+    WrongCellCount(usize),
+    InvalidDirective(String),
+    InvalidCandidates(String),
+    OnLine(usize, Box<ParseError>),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Self::InvalidCharacter(c) => write!(f, "invalid character {c:?} in puzzle"),
+            Self::WrongCellCount(n) => write!(f, "expected 81 cells, found {n}"),
+            Self::InvalidDirective(line) => write!(f, "invalid directive {line:?}"),
+            Self::InvalidCandidates(token) => write!(f, "invalid candidates {token:?}"),
+            Self::OnLine(line, error) => write!(f, "line {line}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses Simple Sudoku's .ss layout, rows like `..5|6.3|9..` with `-----------` between
+/// bands. Blanks may be `.`, `0` or `X`, and separators and whitespace are ignored, so a
+/// plain 81-character line parses too.
+///
+/// # Errors
+///
+/// Fails on characters that aren't digits, blanks or separators, or if there aren't
+/// exactly 81 cells.
+pub fn parse_ss(input: &str) -> Result<Gameboard<9, 9>, ParseError> {
+    let mut cells = vec![];
+
+    for c in input.chars() {
+        match c {
+            '1'..='9' => cells.push(c as u8 - b'0'),
+            '.' | '0' | 'X' | 'x' => cells.push(0),
+            '|' | '-' | '+' => {}
+            c if c.is_whitespace() => {}
+            c => return Err(ParseError::InvalidCharacter(c)),
+        }
+    }
+
+    if cells.len() != 81 {
+        return Err(ParseError::WrongCellCount(cells.len()));
+    }
+
+    let mut state = [[0; 9]; 9];
+
+    for (i, n) in cells.into_iter().enumerate() {
+        state[i / 9][i % 9] = n;
+    }
+
+    Ok(state.into())
+}
+
+/// Parses a .sdm collection, one puzzle per line in any layout [`parse_ss`] takes on
+/// a single line. Blank lines are skipped.
+///
+/// # Errors
+///
+/// Fails on the first line that doesn't parse, with its line number.
+pub fn parse_sdm(input: &str) -> Result<Vec<Gameboard<9, 9>>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_ss(line).map_err(|error| ParseError::OnLine(i + 1, Box::new(error))))
+        .collect()
+}
+
+// the input layouts `detect_format` tells apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Format {
+    // one puzzle on a single 81-character line
+    Line,
+    // a collection, one puzzle per line
+    Sdm,
+    // nine rows of nine cells, after optional `#` comment lines and a `[Puzzle]` header
+    Sdk,
+    // Simple Sudoku's rows like `..5|6.3|9..`
+    Ss,
+    // rows of cells separated by spaces, like the plain output
+    Grid,
+    // a grid with variant directives or saved candidates
+    Extended,
+}
+
+impl Format {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Line => "line",
+            Self::Sdm => "sdm",
+            Self::Sdk => "sdk",
+            Self::Ss => "ss",
+            Self::Grid => "grid",
+            Self::Extended => "extended",
+        }
+    }
+}
+
+// a guess from the shape of the text alone, so a malformed input is only rejected
+// once it's parsed as the format it looks like
+#[must_use]
+pub fn detect_format(input: &str) -> Format {
+    let lines: Vec<&str> = input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    let is_directive = |line: &&str| {
+        line.split_whitespace()
+            .next()
+            .is_some_and(|token| token == "candidates" || Variant::NAMES.contains(&token))
+    };
+
+    if lines.iter().any(is_directive) {
+        Format::Extended
+    } else if lines
+        .iter()
+        .any(|line| line.starts_with('#') || line.starts_with('['))
+    {
+        Format::Sdk
+    } else if lines
+        .iter()
+        .any(|line| line.contains('|') || line.contains("---"))
+    {
+        Format::Ss
+    } else if lines.len() == 1 && lines[0].chars().count() == 81 {
+        Format::Line
+    } else if lines.len() > 1
+        && lines
+            .iter()
+            .all(|line| line.chars().count().abs_diff(81) <= 9)
+    {
+        // a line or two a few cells off is still a collection, and parse_sdm says which
+        Format::Sdm
+    } else if lines.iter().any(|line| line.contains(char::is_whitespace)) {
+        Format::Grid
+    } else {
+        Format::Sdk
+    }
+}
+
+/// Parses `input` as whichever format [`detect_format`] picks. Every format but .sdm
+/// holds one puzzle, and only the extended format carries candidates.
+///
+/// # Errors
+///
+/// Fails if the input doesn't parse as the detected format.
+pub fn parse_detected(
+    input: &str,
+) -> Result<(Format, Vec<(Puzzle, Candidates<9, 9>)>), ParseError> {
+    let format = detect_format(input);
+
+    let classic = |gameboard: Gameboard<9, 9>| (gameboard.into(), Candidates::default());
+
+    let puzzles = match format {
+        Format::Line | Format::Ss | Format::Grid => vec![classic(parse_ss(input)?)],
+        Format::Sdk => vec![classic(parse_sdk(input)?)],
+        Format::Sdm => parse_sdm(input)?.into_iter().map(classic).collect(),
+        Format::Extended => vec![import_state(input)?],
+    };
+
+    Ok((format, puzzles))
+}
+
+/// Parses Sadman Sudoku's .sdk layout, nine rows of nine cells after any `#` comment
+/// lines, optionally under a `[Puzzle]` header.
+///
+/// # Errors
+///
+/// Fails if the rows don't parse as [`parse_ss`] input.
+pub fn parse_sdk(input: &str) -> Result<Gameboard<9, 9>, ParseError> {
+    let grid: Vec<&str> = input
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.trim().starts_with('['))
+        .collect();
+
+    parse_ss(&grid.join("\n"))
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Puzzle {
+    pub gameboard: Gameboard<9, 9>,
+    pub variants: Vec<Variant>,
+}
+
+impl Puzzle {
+    // the board's fingerprint, followed by the variants' directives a line each when
+    // there are any, so a classic puzzle keeps the id `Gameboard::fingerprint` gives it
+    #[must_use]
+    pub fn fingerprint(&self) -> u64 {
+        self.variants
+            .iter()
+            .fold(self.gameboard.fingerprint(), |hash, variant| {
+                fnv1a(hash, format!("{}\n", to_directive(variant)).as_bytes())
+            })
+    }
+}
+
+// a classic puzzle, without variants
+impl From<Gameboard<9, 9>> for Puzzle {
+    fn from(gameboard: Gameboard<9, 9>) -> Self {
+        Self {
+            gameboard,
+            variants: vec![],
+        }
+    }
+}
+
+/// Parses a grid in any layout [`parse_ss`] accepts, with variant constraints declared
+/// on lines of their own:
+///
+/// ```text
+/// whisper <difference> <cell> <cell> ...
+/// renban <cell> <cell> ...
+/// quadruple <cell> <digit> ...
+/// diagonals
+/// anti-knight
+/// killer <sum> <cell> ...
+/// xv x <cell> <cell>
+/// xv v <cell> <cell>
+/// xv negative
+/// clone <cell> ... = <cell> ...
+/// ```
+///
+/// where cells are written `r1c1` to `r9c9` and consecutive cells along a path must
+/// touch, orthogonally or diagonally. A quadruple's cell is the top-left of its 2x2
+/// block and it lists up to four digits, each at most twice. A killer cage lists up to
+/// nine different cells. An X or V joins two orthogonal neighbours, and `xv negative`
+/// rules out the sums on every pair without one. A clone pairs the cells either side of
+/// the `=` in the order they're listed, and the second group has to be the first one
+/// shifted, and maybe turned or mirrored, to match it. Any number of directives can be
+/// combined, with the XV ones gathered into a single [`Variant::Xv`].
+///
+/// # Errors
+///
+/// Fails if the grid doesn't parse, or a directive is malformed.
+pub fn parse_extended(input: &str) -> Result<Puzzle, ParseError> {
+    let mut grid = String::new();
+    let mut variants = vec![];
+
+    for line in input.lines() {
+        let mut tokens = line.split_whitespace();
+
+        let variant = match tokens.next() {
+            Some("whisper") => parse_whisper(tokens),
+            Some("renban") => parse_path(tokens).map(|path| Variant::Renban { path }),
+            Some("quadruple") => parse_quadruple(tokens),
+            Some("diagonals") => tokens.next().is_none().then_some(Variant::Diagonals),
+            Some("anti-knight") => tokens.next().is_none().then_some(Variant::AntiKnight),
+            Some("killer") => parse_killer(tokens),
+            Some("xv") => parse_xv(tokens),
+            Some("clone") => parse_clone(tokens),
+            _ => {
+                grid.push_str(line);
+                grid.push('\n');
+                continue;
+            }
+        };
+
+        match variant.ok_or_else(|| ParseError::InvalidDirective(line.to_string()))? {
+            Variant::Xv {
+                pairs: new_pairs,
+                negative: new_negative,
+            } => match variants
+                .iter_mut()
+                .find(|variant| matches!(variant, Variant::Xv { .. }))
+            {
+                Some(Variant::Xv { pairs, negative }) => {
+                    pairs.extend(new_pairs);
+                    *negative |= new_negative;
+                }
+                _ => variants.push(Variant::Xv {
+                    pairs: new_pairs,
+                    negative: new_negative,
+                }),
+            },
+            variant => variants.push(variant),
+        }
+    }
+
+    Ok(Puzzle {
+        gameboard: parse_ss(&grid)?,
+        variants,
+    })
+}
+
+fn parse_whisper<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<Variant> {
+    let difference = tokens
+        .next()?
+        .parse()
+        .ok()
+        .filter(|n| (1..=8).contains(n))?;
+    let path = parse_path(tokens)?;
+
+    Some(Variant::Whisper { difference, path })
+}
+
+fn parse_quadruple<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<Variant> {
+    let corner = parse_cell(tokens.next()?).filter(|(x, y)| *x < 8 && *y < 8)?;
+
+    let digits = tokens
+        .map(|token| token.parse().ok().filter(|n| (1..=9).contains(n)))
+        .collect::<Option<Vec<u8>>>()?;
+
+    let mut counts = [0; 10];
+
+    for n in &digits {
+        counts[usize::from(*n)] += 1;
+    }
+
+    let valid = (1..=4).contains(&digits.len()) && counts.iter().all(|count| *count <= 2);
+
+    valid.then_some(Variant::Quadruple { corner, digits })
+}
+
+fn parse_killer<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<Variant> {
+    let sum = tokens.next()?.parse().ok()?;
+    let cage = tokens.map(parse_cell).collect::<Option<Vec<_>>>()?;
+
+    let mut distinct = cage.clone();
+    distinct.sort_unstable();
+    distinct.dedup();
+
+    let valid = (1..=9).contains(&cage.len()) && distinct.len() == cage.len();
+
+    valid.then_some(Variant::Killer { sum, cage })
+}
+
+// a single mark, or the negative constraint, merged by `parse_extended`
+fn parse_xv<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<Variant> {
+    let sum = match tokens.next()? {
+        "negative" => {
+            return tokens.next().is_none().then_some(Variant::Xv {
+                pairs: vec![],
+                negative: true,
+            })
+        }
+        "x" => 10,
+        "v" => 5,
+        _ => return None,
+    };
+
+    let cells = [parse_cell(tokens.next()?)?, parse_cell(tokens.next()?)?];
+    let [(x, y), (x2, y2)] = cells;
+
+    let valid = tokens.next().is_none() && x.abs_diff(x2) + y.abs_diff(y2) == 1;
+
+    valid.then(|| Variant::Xv {
+        pairs: vec![(sum, cells)],
+        negative: false,
+    })
+}
+
+fn parse_clone<'a>(tokens: impl Iterator<Item = &'a str>) -> Option<Variant> {
+    let tokens: Vec<&str> = tokens.collect();
+    let (cells, twin) = tokens.split_at(tokens.iter().position(|token| *token == "=")?);
+
+    let cells = cells
+        .iter()
+        .map(|token| parse_cell(token))
+        .collect::<Option<Vec<_>>>()?;
+    let twin = twin[1..]
+        .iter()
+        .map(|token| parse_cell(token))
+        .collect::<Option<Vec<_>>>()?;
+
+    let valid = !cells.is_empty()
+        && cells.len() == twin.len()
+        && cells.iter().zip(&twin).all(|(a, b)| a != b)
+        && (0..8).any(|symmetry| is_moved_copy(&cells, &twin, symmetry));
+
+    valid.then_some(Variant::Clone { cells, twin })
+}
+
+// whether `twin` is `cells` turned or mirrored by one of the square's eight
+// symmetries, then shifted, keeping the listed order
+#[allow(clippy::cast_possible_wrap)]
+fn is_moved_copy(cells: &[(usize, usize)], twin: &[(usize, usize)], symmetry: u8) -> bool {
+    let turn = |(x, y): (usize, usize)| {
+        let (x, y) = (x as isize, y as isize);
+        let (x, y) = if symmetry & 4 == 0 { (x, y) } else { (y, x) };
+
+        (
+            if symmetry & 1 == 0 { x } else { -x },
+            if symmetry & 2 == 0 { y } else { -y },
+        )
+    };
+
+    let offset = |cell: (usize, usize), twin: (usize, usize)| {
+        let (x, y) = turn(cell);
+        (twin.0 as isize - x, twin.1 as isize - y)
+    };
+
+    let shift = offset(cells[0], twin[0]);
+
+    cells
+        .iter()
+        .zip(twin)
+        .all(|(cell, twin)| offset(*cell, *twin) == shift)
+}
+
+fn parse_path<'a>(tokens: impl Iterator<Item = &'a str>) -> Option<Vec<(usize, usize)>> {
+    let path = tokens.map(parse_cell).collect::<Option<Vec<_>>>()?;
+
+    let connected = path.windows(2).all(|pair| {
+        pair[0] != pair[1]
+            && pair[0].0.abs_diff(pair[1].0) <= 1
+            && pair[0].1.abs_diff(pair[1].1) <= 1
+    });
+
+    (path.len() >= 2 && connected).then_some(path)
+}
+
+// `r3c7` is the cell at (2, 6)
+fn parse_cell(token: &str) -> Option<(usize, usize)> {
+    let (row, column) = token.strip_prefix('r')?.split_once('c')?;
+    let (row, column): (usize, usize) = (row.parse().ok()?, column.parse().ok()?);
+
+    ((1..=9).contains(&row) && (1..=9).contains(&column)).then(|| (row - 1, column - 1))
+}
+
+// the grid, one `candidates` line of hex masks per row, then the variant directives,
+// for `import_state` to read back
+#[must_use]
+pub fn export_state(puzzle: &Puzzle, candidates: &Candidates<9, 9>) -> String {
+    let mut output = to_ss(&puzzle.gameboard);
+
+    for row in &candidates.cells {
+        let masks: Vec<String> = row.iter().map(|mask| format!("{mask:03x}")).collect();
+        output.push_str("candidates ");
+        output.push_str(&masks.join(" "));
+        output.push('\n');
+    }
+
+    for variant in &puzzle.variants {
+        output.push_str(&to_directive(variant));
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Reads a state written by [`export_state`]. Without `candidates` lines every cell
+/// starts with all nine digits, so any input [`parse_extended`] takes also works.
+///
+/// # Errors
+///
+/// Fails if the puzzle doesn't parse, or the candidates aren't 81 masks of digits 1 to 9.
+pub fn import_state(input: &str) -> Result<(Puzzle, Candidates<9, 9>), ParseError> {
+    let mut rest = String::new();
+    let mut masks = vec![];
+
+    for line in input.lines() {
+        let Some(tokens) = line.strip_prefix("candidates ") else {
+            rest.push_str(line);
+            rest.push('\n');
+            continue;
+        };
+
+        for token in tokens.split_whitespace() {
+            let mask = CandidateWord::from_str_radix(token, 16)
+                .ok()
+                .filter(|mask| mask >> 9 == 0)
+                .ok_or_else(|| ParseError::InvalidCandidates(token.to_string()))?;
+            masks.push(mask);
+        }
+    }
+
+    let puzzle = parse_extended(&rest)?;
+    let mut candidates = Candidates::default();
+
+    if !masks.is_empty() {
+        if masks.len() != 81 {
+            return Err(ParseError::InvalidCandidates(format!(
+                "{} masks",
+                masks.len()
+            )));
+        }
+
+        for (i, mask) in masks.into_iter().enumerate() {
+            candidates.cells[i / 9][i % 9] = mask;
+        }
+    }
+
+    Ok((puzzle, candidates))
+}
+
+fn to_directive(variant: &Variant) -> String {
+    let cell = |(x, y): &(usize, usize)| format!("r{}c{}", x + 1, y + 1);
+
+    match variant {
+        Variant::Whisper { difference, path } => {
+            let path: Vec<String> = path.iter().map(cell).collect();
+            format!("whisper {difference} {}", path.join(" "))
+        }
+        Variant::Renban { path } => {
+            let path: Vec<String> = path.iter().map(cell).collect();
+            format!("renban {}", path.join(" "))
+        }
+        Variant::Quadruple { corner, digits } => {
+            let digits: Vec<String> = digits.iter().map(u8::to_string).collect();
+            format!("quadruple {} {}", cell(corner), digits.join(" "))
+        }
+        Variant::Diagonals => "diagonals".to_string(),
+        Variant::AntiKnight => "anti-knight".to_string(),
+        Variant::Killer { sum, cage } => {
+            let cage: Vec<String> = cage.iter().map(cell).collect();
+            format!("killer {sum} {}", cage.join(" "))
+        }
+        Variant::Xv { pairs, negative } => {
+            let mut lines: Vec<String> = pairs
+                .iter()
+                .map(|(sum, [a, b])| {
+                    let mark = if *sum == 5 { "v" } else { "x" };
+                    format!("xv {mark} {} {}", cell(a), cell(b))
+                })
+                .collect();
+
+            if *negative {
+                lines.push("xv negative".to_string());
+            }
+
+            lines.join("\n")
+        }
+        Variant::Clone { cells, twin } => {
+            let cells: Vec<String> = cells.iter().map(cell).collect();
+            let twin: Vec<String> = twin.iter().map(cell).collect();
+            format!("clone {} = {}", cells.join(" "), twin.join(" "))
+        }
+    }
+}
+
+#[must_use]
+pub fn to_ss(gameboard: &Gameboard<9, 9>) -> String {
+    let mut output = String::new();
+
+    for x in 0..9 {
+        if x > 0 && x % 3 == 0 {
+            output.push_str("-----------\n");
+        }
+
+        for y in 0..9 {
+            if y > 0 && y % 3 == 0 {
+                output.push('|');
+            }
+
+            output.push(match gameboard.cell(x, y) {
+                0 => '.',
+                n => char::from(b'0' + n),
+            });
+        }
+
+        output.push('\n');
+    }
+
+    output
+}
+
+// the 81 cells on one line with `.` for blanks, as a line of a .sdm collection
+#[must_use]
+pub fn to_line(gameboard: &Gameboard<9, 9>) -> String {
+    gameboard
+        .state
+        .iter()
+        .flatten()
+        .map(|n| match n {
+            0 => '.',
+            n => char::from(b'0' + n),
+        })
+        .collect()
+}
+
+// a collection `parse_sdm` reads back, one line per board
+#[must_use]
+pub fn to_sdm(gameboards: &[Gameboard<9, 9>]) -> String {
+    gameboards
+        .iter()
+        .map(|gameboard| to_line(gameboard) + "\n")
+        .collect()
+}
+
+// the candidates of every empty cell, e.g. `13[7]9` with the `highlights` for that
+// cell in brackets, and the digit of every solved cell. so a stuck board shows where
+// it went wrong, an empty cell with no candidates left is `!` and one whose single
+// candidate hasn't been placed yet is marked `5*`. columns are padded to line up
+#[must_use]
+pub fn to_pencilmarks(
+    gameboard: &Gameboard<9, 9>,
+    candidates: &Candidates<9, 9>,
+    highlights: &[((usize, usize), CandidateSet)],
+) -> String {
+    let cells: Vec<Vec<String>> = (0..9)
+        .map(|x| {
+            (0..9)
+                .map(|y| pencilmark_cell(gameboard, candidates, highlights, (x, y)))
+                .collect()
+        })
+        .collect();
+
+    let widths: Vec<usize> = (0..9)
+        .map(|y| cells.iter().map(|row| row[y].len()).max().unwrap_or(0))
+        .collect();
+
+    let mut output = String::new();
+
+    for (x, row) in cells.iter().enumerate() {
+        if x > 0 && x % 3 == 0 {
+            let width = widths.iter().sum::<usize>() + 9 + 2 * 2;
+            output.push_str(&"-".repeat(width));
+            output.push('\n');
+        }
+
+        for (y, cell) in row.iter().enumerate() {
+            if y > 0 && y % 3 == 0 {
+                output.push_str("| ");
+            }
+
+            output.push_str(cell);
+            output.push_str(&" ".repeat(widths[y] - cell.len() + 1));
+        }
+
+        output.truncate(output.trim_end().len());
+        output.push('\n');
+    }
+
+    output
+}
+
+fn pencilmark_cell(
+    gameboard: &Gameboard<9, 9>,
+    candidates: &Candidates<9, 9>,
+    highlights: &[((usize, usize), CandidateSet)],
+    (x, y): (usize, usize),
+) -> String {
+    if gameboard.cell(x, y) != 0 {
+        return gameboard.cell(x, y).to_string();
+    }
+
+    let remaining = candidates.candidates_at((x, y));
+
+    if remaining.is_empty() {
+        return "!".to_string();
+    }
+
+    let highlighted = CandidateSet(
+        highlights
+            .iter()
+            .filter(|(position, _)| *position == (x, y))
+            .fold(0, |mask, (_, digits)| mask | digits.0),
+    );
+
+    let mut cell: String = remaining
+        .iter()
+        .map(|n| {
+            if highlighted.contains(n) {
+                format!("[{n}]")
+            } else {
+                n.to_string()
+            }
+        })
+        .collect();
+
+    if remaining.len() == 1 {
+        cell.push('*');
+    }
+
+    cell
+}
+
+// quoted, with quotes doubled, when the field holds anything CSV gives a meaning to
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// a JSON string literal, quotes included
+pub(crate) fn json_string(text: &str) -> String {
+    let mut output = String::from('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            // writing to a String can't fail
+            c if c.is_control() => {
+                let _ = write!(output, "\\u{:04x}", u32::from(c));
+            }
+            c => output.push(c),
+        }
+    }
+
+    output.push('"');
+    output
+}
+
+// one row per rating after a header, with techniques separated by `;`
+#[must_use]
+pub fn ratings_to_csv(ratings: &[Rating]) -> String {
+    let mut output = String::from("fingerprint,clues,difficulty,solved,techniques\n");
+
+    for rating in ratings {
+        let row = format!(
+            "{:016x},{},{},{},{}",
+            rating.fingerprint,
+            rating.clues,
+            csv_field(&format!("{:?}", rating.difficulty)),
+            rating.solved,
+            csv_field(&rating.techniques.join(";"))
+        );
+
+        output.push_str(&row);
+        output.push('\n');
+    }
+
+    output
+}
+
+#[must_use]
+pub fn ratings_to_json(ratings: &[Rating]) -> String {
+    let objects: Vec<String> = ratings
+        .iter()
+        .map(|rating| {
+            let techniques: Vec<String> = rating
+                .techniques
+                .iter()
+                .map(|technique| json_string(technique))
+                .collect();
+
+            format!(
+                "{{\"fingerprint\":\"{:016x}\",\"clues\":{},\"difficulty\":{},\"solved\":{},\"techniques\":[{}]}}",
+                rating.fingerprint,
+                rating.clues,
+                json_string(&format!("{:?}", rating.difficulty)),
+                rating.solved,
+                techniques.join(",")
+            )
+        })
+        .collect();
+
+    format!("[{}]\n", objects.join(",\n"))
+}
+
+// Graphviz DOT with a node per cell, givens labelled with their digit, and an edge
+// between every two cells some rule reads together, labelled with the rules. rules
+// that read the whole board, like exclude_when_solved, add no edges
+#[must_use]
+pub fn to_dot(gameboard: &Gameboard<9, 9>, rules: &[Box<dyn Rule<9, 9>>]) -> String {
+    let cell = |(x, y): (usize, usize)| format!("r{}c{}", x + 1, y + 1);
+
+    // keyed by the pair of cells, smaller first
+    let mut edges = BTreeMap::<_, Vec<&'static str>>::new();
+
+    for rule in rules {
+        let Some(scope) = rule.scope() else {
+            continue;
+        };
+
+        for (i, a) in scope.iter().enumerate() {
+            for b in &scope[i + 1..] {
+                let names = edges.entry((*a.min(b), *a.max(b))).or_default();
+
+                if !names.contains(&rule.name()) {
+                    names.push(rule.name());
+                }
+            }
+        }
+    }
+
+    let mut lines = vec!["graph constraints {".to_string()];
+
+    for x in 0..9 {
+        for y in 0..9 {
+            lines.push(match gameboard.cell(x, y) {
+                0 => format!("  {};", cell((x, y))),
+                n => format!("  {} [label=\"{}={n}\"];", cell((x, y)), cell((x, y))),
+            });
+        }
+    }
+
+    for ((a, b), names) in &edges {
+        lines.push(format!(
+            "  {} -- {} [label=\"{}\"];",
+            cell(*a),
+            cell(*b),
+            names.join(", ")
+        ));
+    }
+
+    lines.push("}".to_string());
+
+    lines.join("\n") + "\n"
+}
+
+const BOARD_MAGIC: u8 = b'S';
+const COLLECTION_MAGIC: u8 = b'C';
+
+// variants with no cells of their own, so a bit of the header is enough for them.
+// plain boards and collections always have none set
+const DIAGONALS_FLAG: u8 = 0x01;
+const ANTI_KNIGHT_FLAG: u8 = 0x02;
+const KNOWN_FLAGS: u8 = DIAGONALS_FLAG | ANTI_KNIGHT_FLAG;
+
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EncodeError {
+    // a cell holding more than the board's digit count
+    CellOutOfRange { position: (usize, usize), value: u8 },
+    // a variant with cells of its own, which the header's flags can't describe
+    UnencodableVariant(&'static str),
+    // a collection longer than its u32 count can say
+    TooManyBoards(usize),
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Self::CellOutOfRange {
+                position: (x, y),
+                value,
+            } => write!(f, "r{}c{} holds {value}, which isn't a digit", x + 1, y + 1),
+            Self::UnencodableVariant(name) => write!(f, "{name} constraints can't be encoded"),
+            Self::TooManyBoards(n) => write!(f, "{n} boards are more than a collection can hold"),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeError {
+    BadMagic(u8),
+    SizeMismatch {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    UnknownFlags(u8),
+    // a board with variant flags, which only `puzzle_from_bytes` keeps
+    VariantFlags(u8),
+    CellOutOfRange {
+        position: (usize, usize),
+        value: u8,
+    },
+    Truncated,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Self::BadMagic(magic) => write!(f, "unexpected header byte {magic:#04x}"),
+            Self::SizeMismatch { expected, found } => write!(
+                f,
+                "expected a {}x{} board, found {}x{}",
+                expected.0, expected.1, found.0, found.1
+            ),
+            Self::UnknownFlags(flags) => write!(f, "unknown flags {flags:#010b}"),
+            Self::VariantFlags(flags) => write!(f, "board has variant flags {flags:#010b}"),
+            Self::CellOutOfRange {
+                position: (x, y),
+                value,
+            } => write!(f, "r{}c{} holds {value}, which isn't a digit", x + 1, y + 1),
+            Self::Truncated => write!(f, "input ended early"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Encodes a board as a header of magic, rows, columns and flags, then its cells
+/// packed two to a byte, high nibble first. 4 bits per cell caps boards at 15 digits.
+///
+/// # Errors
+///
+/// Fails if a cell holds more than the board's `Y` digits.
+pub fn to_bytes<const X: usize, const Y: usize>(
+    gameboard: &Gameboard<X, Y>,
+) -> Result<Vec<u8>, EncodeError> {
+    let mut bytes = vec![BOARD_MAGIC];
+    bytes.extend(size_header::<X, Y>());
+    bytes.push(0);
+    pack_cells(gameboard, &mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Decodes a board written by [`to_bytes`].
+///
+/// # Errors
+///
+/// Fails if the header isn't a board header for an `X` by `Y` board without variant
+/// flags, if a cell holds more than `Y`, or if the input is too short.
+pub fn from_bytes<const X: usize, const Y: usize>(
+    bytes: &[u8],
+) -> Result<Gameboard<X, Y>, DecodeError> {
+    let (flags, cells) = read_header::<X, Y>(bytes, BOARD_MAGIC)?;
+
+    if flags != 0 {
+        return Err(DecodeError::VariantFlags(flags));
+    }
+
+    unpack_cells(cells)
+}
+
+/// Encodes a puzzle like [`to_bytes`], with its diagonals and anti-knight constraints as
+/// header flags.
+///
+/// # Errors
+///
+/// Fails if a cell holds more than 9, or the puzzle has a variant with cells of its own.
+pub fn puzzle_to_bytes(puzzle: &Puzzle) -> Result<Vec<u8>, EncodeError> {
+    let mut flags = 0;
+
+    for variant in &puzzle.variants {
+        flags |= match variant {
+            Variant::Diagonals => DIAGONALS_FLAG,
+            Variant::AntiKnight => ANTI_KNIGHT_FLAG,
+            variant => return Err(EncodeError::UnencodableVariant(variant.name())),
+        };
+    }
+
+    let mut bytes = to_bytes(&puzzle.gameboard)?;
+    bytes[3] = flags;
+
+    Ok(bytes)
+}
+
+/// Decodes a puzzle written by [`puzzle_to_bytes`], or a board written by [`to_bytes`].
+///
+/// # Errors
+///
+/// Fails if the header isn't a 9x9 board header or has unknown flags, if a cell holds
+/// more than 9, or if the input is too short.
+pub fn puzzle_from_bytes(bytes: &[u8]) -> Result<Puzzle, DecodeError> {
+    let (flags, cells) = read_header::<9, 9>(bytes, BOARD_MAGIC)?;
+
+    let variants = [
+        (DIAGONALS_FLAG, Variant::Diagonals),
+        (ANTI_KNIGHT_FLAG, Variant::AntiKnight),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| flags & flag != 0)
+    .map(|(_, variant)| variant)
+    .collect();
+
+    Ok(Puzzle {
+        gameboard: unpack_cells(cells)?,
+        variants,
+    })
+}
+
+/// Encodes boards under one header like [`to_bytes`]'s, with a little-endian u32 count
+/// after the flags.
+///
+/// # Errors
+///
+/// Fails if a cell of any board holds more than the boards' `Y` digits, or if there are
+/// more boards than the count can hold.
+pub fn collection_to_bytes<const X: usize, const Y: usize>(
+    gameboards: &[Gameboard<X, Y>],
+) -> Result<Vec<u8>, EncodeError> {
+    let mut bytes = vec![COLLECTION_MAGIC];
+    bytes.extend(size_header::<X, Y>());
+    bytes.push(0);
+    let count = u32::try_from(gameboards.len())
+        .map_err(|_| EncodeError::TooManyBoards(gameboards.len()))?;
+    bytes.extend(count.to_le_bytes());
+
+    for gameboard in gameboards {
+        pack_cells(gameboard, &mut bytes)?;
+    }
+
+    Ok(bytes)
+}
+
+/// Decodes a collection written by [`collection_to_bytes`].
+///
+/// # Errors
+///
+/// Fails if the header isn't a collection header for `X` by `Y` boards without flags,
+/// if a cell holds more than `Y`, or if the input is shorter than the count it declares.
+pub fn collection_from_bytes<const X: usize, const Y: usize>(
+    bytes: &[u8],
+) -> Result<Vec<Gameboard<X, Y>>, DecodeError> {
+    let (flags, rest) = read_header::<X, Y>(bytes, COLLECTION_MAGIC)?;
+
+    if flags != 0 {
+        return Err(DecodeError::VariantFlags(flags));
+    }
+
+    let Some((count, mut rest)) = rest.split_first_chunk::<4>() else {
+        return Err(DecodeError::Truncated);
+    };
+
+    let mut gameboards = vec![];
+
+    for _ in 0..u32::from_le_bytes(*count) {
+        let Some((cells, remaining)) = rest.split_at_checked(packed_len::<X, Y>()) else {
+            return Err(DecodeError::Truncated);
+        };
+
+        gameboards.push(unpack_cells(cells)?);
+        rest = remaining;
+    }
+
+    Ok(gameboards)
+}
+
+fn packed_len<const X: usize, const Y: usize>() -> usize {
+    (X * Y).div_ceil(2)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn size_header<const X: usize, const Y: usize>() -> [u8; 2] {
+    const { assert!(Y <= 15, "4-bit cells only hold digits up to 15") };
+
+    [X as u8, Y as u8]
+}
+
+// the flags and what follows the header
+fn read_header<const X: usize, const Y: usize>(
+    bytes: &[u8],
+    magic: u8,
+) -> Result<(u8, &[u8]), DecodeError> {
+    let Some(([found_magic, x, y, flags], rest)) = bytes.split_first_chunk::<4>() else {
+        return Err(DecodeError::Truncated);
+    };
+
+    if *found_magic != magic {
+        return Err(DecodeError::BadMagic(*found_magic));
+    }
+
+    if (usize::from(*x), usize::from(*y)) != (X, Y) {
+        return Err(DecodeError::SizeMismatch {
+            expected: (X, Y),
+            found: (usize::from(*x), usize::from(*y)),
+        });
+    }
+
+    if *flags & !KNOWN_FLAGS != 0 {
+        return Err(DecodeError::UnknownFlags(*flags));
+    }
+
+    Ok((*flags, rest))
+}
+
+fn pack_cells<const X: usize, const Y: usize>(
+    gameboard: &Gameboard<X, Y>,
+    bytes: &mut Vec<u8>,
+) -> Result<(), EncodeError> {
+    let cells: Vec<u8> = gameboard.state.iter().flatten().copied().collect();
+
+    if let Some(i) = cells.iter().position(|n| usize::from(*n) > Y) {
+        return Err(EncodeError::CellOutOfRange {
+            position: (i / Y, i % Y),
+            value: cells[i],
+        });
+    }
+
+    for pair in cells.chunks(2) {
+        bytes.push(pair[0] << 4 | pair.get(1).copied().unwrap_or(0));
+    }
+
+    Ok(())
+}
+
+fn unpack_cells<const X: usize, const Y: usize>(
+    bytes: &[u8],
+) -> Result<Gameboard<X, Y>, DecodeError> {
+    if bytes.len() < packed_len::<X, Y>() {
+        return Err(DecodeError::Truncated);
+    }
+
+    let mut state = [[0; Y]; X];
+
+    let nibbles = bytes.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]);
+
+    for (i, (cell, n)) in state.iter_mut().flatten().zip(nibbles).enumerate() {
+        if usize::from(n) > Y {
+            return Err(DecodeError::CellOutOfRange {
+                position: (i / Y, i % Y),
+                value: n,
+            });
+        }
+
+        *cell = n;
+    }
+
+    Ok(state.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str =
+        "...8.....5.63.9...84...27..3.1...5.5...3...2.6...5.1..19...56...84.27.......6....";
+
+    #[test]
+    fn ss_round_trips() {
+        let gameboard = parse_ss(PUZZLE).unwrap();
+        let ss = to_ss(&gameboard);
+
+        assert_eq!(ss.lines().next(), Some("...|8..|..."));
+        assert_eq!(ss.lines().nth(3), Some("-----------"));
+        assert_eq!(parse_ss(&ss).unwrap().state, gameboard.state);
+
+        assert_eq!(parse_ss("12").unwrap_err(), ParseError::WrongCellCount(2));
+        assert_eq!(
+            parse_ss(&PUZZLE.replace('3', "a")).unwrap_err(),
+            ParseError::InvalidCharacter('a')
+        );
+    }
+
+    #[test]
+    fn board_round_trips() {
+        let gameboard = parse_ss(PUZZLE).unwrap();
+        let bytes = to_bytes(&gameboard).unwrap();
+
+        assert_eq!(bytes.len(), 4 + 41);
+        assert_eq!(from_bytes::<9, 9>(&bytes).unwrap().state, gameboard.state);
+    }
+
+    #[test]
+    fn odd_sized_board_round_trips() {
+        let gameboard = Gameboard::from([[1, 0, 3], [0, 2, 0], [3, 0, 1]]);
+        let bytes = to_bytes(&gameboard).unwrap();
+
+        assert_eq!(from_bytes::<3, 3>(&bytes).unwrap().state, gameboard.state);
+    }
+
+    #[test]
+    fn collection_round_trips() {
+        let first = parse_ss(PUZZLE).unwrap();
+        let mut second = first.clone();
+        second.force_cell(0, 0, 7);
+
+        let bytes = collection_to_bytes(&[first.clone(), second.clone()]).unwrap();
+        let decoded = collection_from_bytes::<9, 9>(&bytes).unwrap();
+
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].state, first.state);
+        assert_eq!(decoded[1].state, second.state);
+    }
+
+    #[test]
+    fn rejects_nibbles_past_the_digits() {
+        let mut bytes = to_bytes(&parse_ss(PUZZLE).unwrap()).unwrap();
+        bytes[4] = 0x0c;
+
+        assert_eq!(
+            from_bytes::<9, 9>(&bytes).unwrap_err(),
+            DecodeError::CellOutOfRange {
+                position: (0, 1),
+                value: 12
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_cells_past_the_digits() {
+        let mut gameboard = parse_ss(PUZZLE).unwrap();
+        gameboard.force_cell(2, 4, 10);
+
+        assert_eq!(
+            to_bytes(&gameboard).unwrap_err(),
+            EncodeError::CellOutOfRange {
+                position: (2, 4),
+                value: 10
+            }
+        );
+    }
+
+    #[test]
+    fn parse_extended_reads_whisper_lines() {
+        let puzzle = parse_extended(&format!("{PUZZLE}\nwhisper 5 r1c1 r2c2 r2c3\n")).unwrap();
+
+        assert_eq!(puzzle.gameboard.state, parse_ss(PUZZLE).unwrap().state);
+        assert_eq!(
+            puzzle.variants,
+            [Variant::Whisper {
+                difference: 5,
+                path: vec![(0, 0), (1, 1), (1, 2)]
+            }]
+        );
+
+        for directive in [
+            "whisper 9 r1c1 r1c2",
+            "whisper 5 r1c1 r1c3",
+            "whisper 5 r1c1",
+            "whisper 5 r0c1 r1c1",
+        ] {
+            assert_eq!(
+                parse_extended(&format!("{PUZZLE}\n{directive}")).unwrap_err(),
+                ParseError::InvalidDirective(directive.to_string())
+            );
+        }
+
+        assert_eq!(
+            parse_extended("whisper 5 r1c1 r1c2").unwrap_err(),
+            ParseError::WrongCellCount(0)
+        );
+    }
+
+    #[test]
+    fn parse_extended_reads_quadruples() {
+        let puzzle = parse_extended(&format!("{PUZZLE}\nquadruple r8c8 1 1 2")).unwrap();
+
+        assert_eq!(
+            puzzle.variants,
+            [Variant::Quadruple {
+                corner: (7, 7),
+                digits: vec![1, 1, 2]
+            }]
+        );
+
+        for directive in [
+            "quadruple r9c1 1",
+            "quadruple r1c1 1 1 1",
+            "quadruple r1c1 1 2 3 4 5",
+        ] {
+            assert_eq!(
+                parse_extended(&format!("{PUZZLE}\n{directive}")).unwrap_err(),
+                ParseError::InvalidDirective(directive.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let puzzle = parse_extended(&format!("{PUZZLE}\nrenban r1c1 r1c2 r1c3")).unwrap();
+        let mut candidates = Candidates::default();
+        candidates.exclude_candidate(0, 0, 4);
+        candidates.set_exclusive_candidate(8, 8, 2);
+
+        let (imported, imported_candidates) =
+            import_state(&export_state(&puzzle, &candidates)).unwrap();
+
+        assert_eq!(imported.gameboard.state, puzzle.gameboard.state);
+        assert_eq!(imported.variants, puzzle.variants);
+        assert_eq!(imported_candidates.cells, candidates.cells);
+
+        let bad = format!("{PUZZLE}\ncandidates 1ff zz");
+        assert_eq!(
+            import_state(&bad).unwrap_err(),
+            ParseError::InvalidCandidates("zz".to_string())
+        );
+    }
+
+    #[test]
+    fn ratings_escape_their_text() {
+        let rating = Rating {
+            fingerprint: 1,
+            clues: 17,
+            difficulty: crate::Difficulty::Hard,
+            techniques: vec!["odd, \"quoted\"\nname".to_string(), "plain".to_string()],
+            solved: true,
+        };
+
+        assert_eq!(
+            ratings_to_csv(std::slice::from_ref(&rating)).lines().nth(1),
+            Some("0000000000000001,17,Hard,true,\"odd, \"\"quoted\"\"")
+        );
+        assert!(ratings_to_csv(std::slice::from_ref(&rating)).ends_with("name;plain\"\n"));
+        assert_eq!(
+            ratings_to_json(&[rating]),
+            "[{\"fingerprint\":\"0000000000000001\",\"clues\":17,\"difficulty\":\"Hard\",\"solved\":true,\"techniques\":[\"odd, \\\"quoted\\\"\\nname\",\"plain\"]}]\n"
+        );
+    }
+
+    #[test]
+    fn dot_joins_the_cells_each_rule_reads() {
+        let dot = to_dot(&parse_ss(PUZZLE).unwrap(), &crate::build_9x9_rules());
+        let edges: Vec<&str> = dot.lines().filter(|line| line.contains(" -- ")).collect();
+
+        // each cell shares a row, column or box with 20 others
+        assert_eq!(edges.len(), 81 * 20 / 2);
+        assert!(
+            edges.contains(&"  r1c1 -- r1c2 [label=\"unique_by_region, fill_region_uniquely\"];")
+        );
+        assert!(!dot.contains("r1c1 -- r2c4"));
+        assert!(dot.contains("  r1c4 [label=\"r1c4=8\"];"));
+        assert!(dot.starts_with("graph constraints {\n") && dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn parse_extended_reads_killer_cages_and_the_grid_variants() {
+        let input = format!("{PUZZLE}\ndiagonals\nanti-knight\nkiller 3 r1c1 r1c2");
+
+        assert_eq!(
+            parse_extended(&input).unwrap().variants,
+            [
+                Variant::Diagonals,
+                Variant::AntiKnight,
+                Variant::Killer {
+                    sum: 3,
+                    cage: vec![(0, 0), (0, 1)]
+                }
+            ]
+        );
+
+        for directive in ["killer 3 r1c1 r1c1", "killer three r1c1", "diagonals both"] {
+            assert_eq!(
+                parse_extended(&format!("{PUZZLE}\n{directive}")).unwrap_err(),
+                ParseError::InvalidDirective(directive.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn variant_flags_round_trip() {
+        let puzzle = Puzzle {
+            gameboard: parse_ss(PUZZLE).unwrap(),
+            variants: vec![Variant::Diagonals, Variant::AntiKnight],
+        };
+        let bytes = puzzle_to_bytes(&puzzle).unwrap();
+        let decoded = puzzle_from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.variants, puzzle.variants);
+        assert_eq!(decoded.gameboard.state, puzzle.gameboard.state);
+        assert_eq!(
+            from_bytes::<9, 9>(&bytes).unwrap_err(),
+            DecodeError::VariantFlags(0x03)
+        );
+    }
+
+    #[test]
+    fn rejects_variants_with_cells() {
+        let puzzle = Puzzle {
+            gameboard: parse_ss(PUZZLE).unwrap(),
+            variants: vec![Variant::Killer {
+                sum: 3,
+                cage: vec![(0, 0), (0, 1)],
+            }],
+        };
+
+        assert_eq!(
+            puzzle_to_bytes(&puzzle).unwrap_err(),
+            EncodeError::UnencodableVariant("killer")
+        );
+    }
+
+    #[test]
+    fn short_line_in_a_collection_is_reported_by_number() {
+        let input = format!("{PUZZLE}\n{PUZZLE}\n{}\n", &PUZZLE[1..]);
+
+        assert_eq!(detect_format(&input), Format::Sdm);
+        assert!(matches!(parse_sdm(&input), Err(ParseError::OnLine(3, _))));
+    }
+
+    #[test]
+    fn detects_each_format() {
+        let gameboard = parse_ss(PUZZLE).unwrap();
+        let rows: Vec<&str> = (0..9).map(|x| &PUZZLE[x * 9..x * 9 + 9]).collect();
+
+        for (input, format) in [
+            (PUZZLE.to_string(), Format::Line),
+            (format!("{PUZZLE}\n{PUZZLE}\n"), Format::Sdm),
+            (
+                format!("# from a file\n[Puzzle]\n{}", rows.join("\n")),
+                Format::Sdk,
+            ),
+            (to_ss(&gameboard), Format::Ss),
+            (gameboard.to_string(), Format::Grid),
+            (format!("{PUZZLE}\ndiagonals"), Format::Extended),
+        ] {
+            assert_eq!(detect_format(&input), format, "{input}");
+
+            let (detected, puzzles) = parse_detected(&input).unwrap();
+            assert_eq!(detected, format);
+            assert!(puzzles
+                .iter()
+                .all(|(puzzle, _)| puzzle.gameboard.state == gameboard.state));
+        }
+    }
+
+    #[test]
+    fn parse_extended_merges_xv_marks() {
+        let input = format!("{PUZZLE}\nxv x r1c1 r1c2\nxv negative\nxv v r2c1 r3c1");
+
+        assert_eq!(
+            parse_extended(&input).unwrap().variants,
+            [Variant::Xv {
+                pairs: vec![(10, [(0, 0), (0, 1)]), (5, [(1, 0), (2, 0)])],
+                negative: true
+            }]
+        );
+
+        for directive in ["xv x r1c1 r2c2", "xv y r1c1 r1c2", "xv negative r1c1"] {
+            assert_eq!(
+                parse_extended(&format!("{PUZZLE}\n{directive}")).unwrap_err(),
+                ParseError::InvalidDirective(directive.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn clones_have_to_be_moved_copies() {
+        let clone = |directive: &str| parse_extended(&format!("{PUZZLE}\nclone {directive}"));
+
+        // shifted, and turned a quarter
+        assert!(clone("r1c1 r1c2 r2c1 = r7c7 r7c8 r8c7").is_ok());
+        assert!(clone("r1c1 r1c2 r2c1 = r7c8 r8c8 r7c7").is_ok());
+
+        for directive in [
+            "r1c1 r1c2 r2c1 = r7c7 r8c7 r7c9",
+            "r1c1 r1c2 = r1c1 r1c3",
+            "r1c1 r1c2 = r9c9",
+        ] {
+            assert_eq!(
+                clone(directive).unwrap_err(),
+                ParseError::InvalidDirective(format!("clone {directive}"))
+            );
+        }
+    }
+
+    #[test]
+    fn variants_change_the_puzzle_fingerprint() {
+        let classic = Puzzle::from(parse_ss(PUZZLE).unwrap());
+        let diagonals = Puzzle {
+            variants: vec![Variant::Diagonals],
+            ..classic.clone()
+        };
+        let anti_knight = Puzzle {
+            variants: vec![Variant::AntiKnight],
+            ..classic.clone()
+        };
+
+        assert_eq!(classic.fingerprint(), classic.gameboard.fingerprint());
+        assert_ne!(diagonals.fingerprint(), classic.fingerprint());
+        assert_ne!(diagonals.fingerprint(), anti_knight.fingerprint());
+    }
+
+    #[test]
+    fn sdm_round_trips() {
+        let gameboards = parse_sdm(&format!("{PUZZLE}\n\n{}\n", PUZZLE.replace('.', "0"))).unwrap();
+        let sdm = to_sdm(&gameboards);
+
+        assert_eq!(sdm, format!("{PUZZLE}\n{PUZZLE}\n"));
+        assert_eq!(parse_sdm(&sdm).unwrap().len(), 2);
+    }
+}
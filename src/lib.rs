@@ -0,0 +1,43 @@
+#![deny(clippy::pedantic)]
+
+mod analysis;
+mod candidates;
+mod engine;
+mod fuzz;
+mod gameboard;
+pub mod io;
+mod patterns;
+pub mod prelude;
+mod render;
+mod rules;
+mod search;
+mod solver;
+mod trace;
+mod variants;
+
+pub use analysis::{
+    branching, find_backdoor, rate, rate_with, Branching, ClueStats, Difficulty, Rating, Symmetry,
+    MAX_BACKDOOR_SIZE,
+};
+pub use candidates::{CandidateSet, CandidateWord, Candidates, JournalEntry, JournalMark};
+pub use engine::{Engine, RuleStats, SolveHandle, SolverConfig, Stats, StopPoint, Strategy};
+pub use fuzz::{cross_check, fuzz_solve, Divergence, FuzzFailure};
+pub use gameboard::{Gameboard, LockedCell};
+pub use render::{
+    Json, Latex, Pencilmarks, Plain, Pretty, RenderOptions, Renderer, Renderers, SimpleSudoku, Svg,
+};
+pub use rules::{
+    build_9x9_regions, build_9x9_rules, build_9x9_rules_with_boxes, build_regions, build_rules,
+    is_consistent, is_valid_solution, validate_boxes, ExcludeWhenSolved, FillRegionUniquely,
+    Region, RegionError, Rule, UniqueByRegion,
+};
+pub use search::{CellOrder, ValueOrder};
+pub use solver::{
+    solve_str, Backend, Capabilities, Hint, ReusableSolver, SolveResult, Solver, SudokuError,
+    Technique,
+};
+pub use trace::{History, Observer, Placement, Tee, Trace, TraceStep};
+pub use variants::{
+    build_9x9_rules_with, AntiKnight, CloneRegion, KillerCage, Quadruple, RenbanLine, Variant,
+    WhisperLine, XvNegative, XvSum,
+};
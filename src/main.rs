@@ -1,274 +1,816 @@
 #![deny(clippy::pedantic)]
 
 use std::{
-    fmt::{Display, Error, Formatter},
-    rc::Rc,
+    collections::{hash_map::Entry, HashMap},
+    env,
+    fs::{self, File},
+    io::{BufWriter, IsTerminal},
+    iter::Peekable,
+    path::Path,
+    thread,
+    time::Duration,
 };
 
-#[derive(Debug)]
-struct Gameboard<const X: usize, const Y: usize> {
-    state: [[u8; X]; Y],
+use rust_sudoku_solver::{
+    branching, build_9x9_rules_with, cross_check, find_backdoor, fuzz_solve, io, rate_with,
+    Backend, Candidates, CellOrder, Gameboard, Hint, History, Placement, Pretty, Rating,
+    RenderOptions, Renderers, SolveHandle, Solver, SolverConfig, Stats, Strategy, Tee, Trace,
+    ValueOrder, MAX_BACKDOOR_SIZE,
+};
+
+enum Command {
+    Solve,
+    Analyze,
+    // dev check that the rules never rule out the solution of a shuffled puzzle
+    FuzzSolve,
+    // rates every puzzle in a .sdm collection
+    Rate,
+    // checks the rules against a plain search on every puzzle in the input
+    CrossCheck,
+    // compares each puzzle's solve path with the one saved for it
+    Snapshot,
+    // drops the puzzles of a .sdm collection that are disguised copies of earlier ones
+    Dedupe,
+    ListTechniques,
 }
 
-impl<const X: usize, const Y: usize> Gameboard<X, Y> {
-    fn set_cell(&mut self, x: usize, y: usize, value: u8) {
-        self.state[x][y] = value;
+// bools for the on/off flags
+#[allow(clippy::struct_excessive_bools)]
+struct Options {
+    command: Command,
+    history: Option<History>,
+    estimate: bool,
+    backdoor: bool,
+    hint: bool,
+    print_stats: bool,
+    highlight: bool,
+    // reports the detected input format on stderr
+    verbose: bool,
+    config: SolverConfig,
+    input: Option<String>,
+    save_state: Option<String>,
+    output: Option<String>,
+    // where analyze writes the constraint graph
+    graph: Option<String>,
+    // how long solve runs before it's cancelled
+    timeout: Option<Duration>,
+    // a renderer name, checked against the registry before solving
+    format: String,
+    renderers: Renderers,
+    // where snapshot keeps a file per puzzle, named by fingerprint
+    snapshots: String,
+    // snapshot saves the current paths instead of failing on changed ones
+    update: bool,
+    rounds: usize,
+    seed: u64,
+}
+
+const DEFAULT_PUZZLE: [[u8; 9]; 9] = [
+    [0, 0, 0, 0, 8, 0, 0, 0, 0],
+    [0, 0, 5, 6, 0, 3, 9, 0, 0],
+    [0, 8, 4, 0, 0, 0, 2, 7, 0],
+    [0, 3, 0, 1, 0, 0, 0, 5, 0],
+    [5, 0, 0, 0, 3, 0, 0, 0, 2],
+    [0, 6, 0, 0, 0, 5, 0, 1, 0],
+    [0, 1, 9, 0, 0, 0, 5, 6, 0],
+    [0, 0, 8, 4, 0, 2, 7, 0, 0],
+    [0, 0, 0, 0, 6, 0, 0, 0, 0],
+];
+
+impl Command {
+    // a leading subcommand, or solving if there isn't one
+    fn parse(args: &mut Peekable<impl Iterator<Item = String>>) -> Self {
+        let command = match args.peek().map(String::as_str) {
+            Some("analyze") => Self::Analyze,
+            Some("fuzz-solve") => Self::FuzzSolve,
+            Some("rate") => Self::Rate,
+            Some("snapshot") => Self::Snapshot,
+            Some("dedupe") => Self::Dedupe,
+            _ => return Self::Solve,
+        };
+
+        args.next();
+
+        command
     }
 }
 
-impl<const X: usize, const Y: usize> Display for Gameboard<X, Y> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        for x in 0..X {
-            for y in 0..Y {
-                write!(
-                    f,
-                    "{} ",
-                    match self.state[x][y] {
-                        0 => ".".to_string(),
-                        a => format!("{a}"),
-                    },
-                )?;
+impl Options {
+    fn new(command: Command) -> Self {
+        Self {
+            command,
+            history: None,
+            estimate: false,
+            backdoor: false,
+            hint: false,
+            print_stats: false,
+            highlight: false,
+            verbose: false,
+            config: SolverConfig::default(),
+            input: None,
+            save_state: None,
+            output: None,
+            graph: None,
+            timeout: None,
+            format: "plain".to_string(),
+            renderers: Renderers::default(),
+            snapshots: "snapshots".to_string(),
+            update: false,
+            rounds: 100,
+            seed: 1,
+        }
+    }
+
+    fn parse(args: impl Iterator<Item = String>) -> std::io::Result<Self> {
+        let mut args = args.peekable();
+
+        let mut options = Self::new(Command::parse(&mut args));
+
+        while let Some(arg) = args.next() {
+            let flag = arg.as_str();
+
+            match flag {
+                "--history" => {
+                    let path = value(&mut args, flag, "a path", |path| Some(path.to_string()))?;
+                    let writer = BufWriter::new(File::create(path)?);
+                    options.history = Some(History::new(Box::new(writer)));
+                }
+                "--strategy" => {
+                    options.config.strategy = value(
+                        &mut args,
+                        flag,
+                        "one of round-robin, cheapest-first, priority-queue, adaptive",
+                        Strategy::parse,
+                    )?;
+                }
+                "--backend" => {
+                    options.config.backend = value(
+                        &mut args,
+                        flag,
+                        "one of logic, search, hybrid",
+                        Backend::parse,
+                    )?;
+                }
+                "--cell-order" => {
+                    options.config.cell_order =
+                        value(&mut args, flag, "one of mrv, first-unset", CellOrder::parse)?;
+                }
+                "--value-order" => {
+                    options.config.value_order =
+                        value(&mut args, flag, "one of ascending, lcv", ValueOrder::parse)?;
+                }
+                "--rule-order" => {
+                    options.config.rule_order =
+                        value(&mut args, flag, "comma-separated rule indices", |order| {
+                            order.split(',').map(|i| i.parse().ok()).collect()
+                        })?;
+                }
+                // checked against the puzzle's techniques once it's loaded
+                "--stop-at" => {
+                    options.config.stop_at = Some(value(&mut args, flag, "a technique", |name| {
+                        Some(name.to_string())
+                    })?);
+                }
+                "--stats" => options.print_stats = true,
+                "--highlight" => options.highlight = true,
+                "--verbose" => options.verbose = true,
+                "--list-techniques" => options.command = Command::ListTechniques,
+                "--cross-check" => options.command = Command::CrossCheck,
+                "--estimate" => options.estimate = true,
+                "--input" => {
+                    let path = value(&mut args, flag, "a path", |path| Some(path.to_string()))?;
+                    options.input = Some(fs::read_to_string(path)?);
+                }
+                "--save-state" => {
+                    options.save_state = Some(value(&mut args, flag, "a path", |path| {
+                        Some(path.to_string())
+                    })?);
+                }
+                "--output" => {
+                    options.output = Some(value(&mut args, flag, "a path", |path| {
+                        Some(path.to_string())
+                    })?);
+                }
+                "--timeout" => {
+                    let ms = value(&mut args, flag, "milliseconds", |ms| ms.parse().ok())?;
+                    options.timeout = Some(Duration::from_millis(ms));
+                }
+                "--format" => {
+                    options.format = value(&mut args, flag, "a format", |format| {
+                        Some(format.to_string())
+                    })?;
+                }
+                "--digit-counts" => {
+                    options
+                        .renderers
+                        .register(Box::new(Pretty { digit_counts: true }));
+                }
+                _ if options.parse_command_flag(flag, &mut args)? => {}
+                _ => return Err(invalid_input(format!("unknown argument: {arg}"))),
             }
-            writeln!(f)?;
         }
 
-        Ok(())
+        Ok(options)
     }
-}
 
-impl<const X: usize, const Y: usize> From<[[u8; X]; Y]> for Gameboard<X, Y> {
-    fn from(value: [[u8; X]; Y]) -> Self {
-        Self { state: value }
+    // flags only some commands take. false if `flag` isn't one of them
+    fn parse_command_flag(
+        &mut self,
+        flag: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> std::io::Result<bool> {
+        match flag {
+            "--backdoor" if matches!(self.command, Command::Analyze) => {
+                self.backdoor = true;
+            }
+            "--hint" if matches!(self.command, Command::Analyze) => {
+                self.hint = true;
+            }
+            "--graph" if matches!(self.command, Command::Analyze) => {
+                self.graph = Some(value(args, flag, "a path", |path| Some(path.to_string()))?);
+            }
+            "--snapshots" if matches!(self.command, Command::Snapshot) => {
+                self.snapshots = value(args, flag, "a directory", |path| Some(path.to_string()))?;
+            }
+            "--update" if matches!(self.command, Command::Snapshot) => {
+                self.update = true;
+            }
+            "--rounds" if matches!(self.command, Command::FuzzSolve) => {
+                self.rounds = value(args, flag, "a number", |n| n.parse().ok())?;
+            }
+            "--seed" if matches!(self.command, Command::FuzzSolve) => {
+                self.seed = value(args, flag, "a number", |n| n.parse().ok())?;
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
     }
-}
 
-trait ToCellMask {
-    fn to_cell_mask(self) -> u16;
-}
+    // input may be in any format `io::detect_format` knows, including a state saved
+    // part way through a solve
+    fn puzzles(&self) -> std::io::Result<Vec<(io::Puzzle, Candidates<9, 9>)>> {
+        let Some(input) = &self.input else {
+            return Ok(vec![(
+                io::Puzzle::from(Gameboard::from(DEFAULT_PUZZLE)),
+                Candidates::default(),
+            )]);
+        };
 
-trait ToResult {
-    fn to_result(self) -> u8;
-}
+        let (format, puzzles) = io::parse_detected(input).map_err(invalid_data)?;
 
-#[derive(Debug)]
-struct Candidates<const X: usize, const Y: usize> {
-    cells: [[u16; Y]; X],
-}
+        if self.verbose {
+            eprintln!("input format: {}", format.name());
+        }
 
-impl<const X: usize, const Y: usize> Default for Candidates<X, Y> {
-    fn default() -> Self {
-        Self {
-            cells: [[511_u16; Y]; X], // 0b111_111_111
+        Ok(puzzles)
+    }
+
+    // rate, cross-check, snapshot and dedupe take collections, the rest a single puzzle
+    fn puzzle(&self) -> std::io::Result<(io::Puzzle, Candidates<9, 9>)> {
+        let mut puzzles = self.puzzles()?;
+
+        if puzzles.len() != 1 {
+            return Err(invalid_input(format!(
+                "expected one puzzle, found {}; rate, cross-check, snapshot and dedupe take collections",
+                puzzles.len()
+            )));
         }
+
+        Ok(puzzles.remove(0))
     }
 }
 
-impl ToCellMask for u8 {
-    fn to_cell_mask(self) -> u16 {
-        1 << (self - 1)
+fn main() -> std::io::Result<()> {
+    let options = Options::parse(env::args().skip(1))?;
+
+    match options.command {
+        Command::ListTechniques => {
+            list_techniques();
+            return Ok(());
+        }
+        Command::Rate => return rate(&options),
+        Command::CrossCheck => return cross_check_all(&options),
+        Command::Snapshot => return snapshot(&options),
+        Command::Dedupe => return dedupe(&options),
+        _ => {}
     }
-}
 
-impl ToResult for u16 {
-    #[allow(clippy::cast_possible_truncation)]
-    fn to_result(self) -> u8 {
-        self.ilog2() as u8 + 1
+    let (puzzle, candidates) = options.puzzle()?;
+
+    match options.command {
+        Command::Analyze => analyze(&options, &puzzle, &candidates),
+        Command::FuzzSolve => fuzz(&options, &puzzle),
+        _ => solve(options, puzzle, candidates),
     }
 }
 
-impl<const X: usize, const Y: usize> Candidates<X, Y> {
-    fn apply_uniques(&self, gameboard: &mut Gameboard<X, Y>) -> bool {
-        let mut changes_made = false;
+fn list_techniques() {
+    let capabilities = Solver::capabilities();
 
-        for x in 0..X {
-            for y in 0..Y {
-                if self.remaining_candidates(x, y) == 1 {
-                    gameboard.set_cell(x, y, self.cells[x][y].to_result());
-                    changes_made = true;
-                }
+    println!("techniques: {}", capabilities.techniques.join(", "));
+    println!("variants: {}", capabilities.variants.join(", "));
+    println!("strategies: {}", capabilities.strategies.join(", "));
+    println!("backends: {}", capabilities.backends.join(", "));
+}
+
+fn analyze(
+    options: &Options,
+    puzzle: &io::Puzzle,
+    candidates: &Candidates<9, 9>,
+) -> std::io::Result<()> {
+    println!("fingerprint: {:016x}", puzzle.fingerprint());
+
+    let clue_stats = puzzle.gameboard.clue_stats();
+    let missing: Vec<String> = clue_stats
+        .missing_digits
+        .iter()
+        .map(u8::to_string)
+        .collect();
+    let box_clues: Vec<String> = clue_stats.box_clues.iter().map(usize::to_string).collect();
+    let symmetries: Vec<&str> = clue_stats
+        .symmetries
+        .iter()
+        .map(|symmetry| symmetry.name())
+        .collect();
+
+    println!("clues: {}", clue_stats.clues);
+    println!("missing digits: {}", or_none(&missing.join(", ")));
+    println!("box clues: {}", box_clues.join(" "));
+    println!("symmetry: {}", or_none(&symmetries.join(", ")));
+    println!(
+        "estimated difficulty: {:?}",
+        puzzle.gameboard.estimate_difficulty()
+    );
+
+    let mut trace = Trace::default();
+
+    Solver::new(options.config.clone(), &puzzle.variants)
+        .solve(&mut puzzle.gameboard.clone(), Some(&mut trace));
+
+    println!(
+        "pleasantness: {}",
+        trace.pleasantness(&build_9x9_rules_with(&puzzle.variants))
+    );
+    println!(
+        "singles: {} naked, {} hidden",
+        trace.count_placements(Placement::NakedSingle),
+        trace.count_placements(Placement::HiddenSingle)
+    );
+
+    let branching = branching(&puzzle.gameboard, options.config.clone(), &puzzle.variants);
+
+    println!(
+        "moves available: min {}, average {:.1} over {} states",
+        branching.min, branching.average, branching.states
+    );
+
+    if options.backdoor {
+        match find_backdoor(&puzzle.gameboard, options.config.clone(), MAX_BACKDOOR_SIZE) {
+            Some(guesses) => {
+                let cells: Vec<String> = guesses
+                    .iter()
+                    .map(|(x, y, n)| format!("r{}c{}={n}", x + 1, y + 1))
+                    .collect();
+                println!("backdoor size: {} ({})", guesses.len(), cells.join(", "));
             }
+            None => println!("backdoor size: > {MAX_BACKDOOR_SIZE}"),
         }
+    }
 
-        changes_made
+    if options.hint {
+        print_hint(options, puzzle, candidates);
     }
 
-    fn exclude_candidate(&mut self, x: usize, y: usize, candidate: u8) {
-        self.cells[x][y] &= !candidate.to_cell_mask();
+    if let Some(path) = &options.graph {
+        let rules = build_9x9_rules_with(&puzzle.variants);
+        fs::write(path, io::to_dot(&puzzle.gameboard, &rules))?;
     }
-    fn set_exclusive_candidate(&mut self, x: usize, y: usize, candidate: u8) {
-        self.cells[x][y] = candidate.to_cell_mask();
+
+    Ok(())
+}
+
+// from the input's state, so a saved solve gets a hint for where it left off
+fn print_hint(options: &Options, puzzle: &io::Puzzle, candidates: &Candidates<9, 9>) {
+    let cell = |(x, y): (usize, usize)| format!("r{}c{}", x + 1, y + 1);
+
+    let solver = Solver::new(options.config.clone(), &puzzle.variants);
+
+    match solver.hint(&puzzle.gameboard, candidates, true) {
+        Some(Hint::Place {
+            position,
+            digit,
+            kind,
+        }) => println!("hint: {digit} at {} ({kind:?})", cell(position)),
+        Some(Hint::Eliminate {
+            technique,
+            cells,
+            eliminations,
+        }) => {
+            let cells: Vec<String> = cells.into_iter().map(cell).collect();
+            let eliminations: Vec<String> = eliminations
+                .into_iter()
+                .map(|(position, n)| format!("{}<>{n}", cell(position)))
+                .collect();
+
+            println!(
+                "hint: {technique} on {} removes {}",
+                cells.join(" "),
+                eliminations.join(", ")
+            );
+        }
+        Some(_) | None => println!("hint: none"),
     }
-    fn remaining_candidates(&self, x: usize, y: usize) -> u32 {
-        self.cells[x][y].count_ones()
+}
+
+fn cross_check_all(options: &Options) -> std::io::Result<()> {
+    let mut divergences = 0;
+
+    for (i, (puzzle, _)) in options.puzzles()?.iter().enumerate() {
+        match cross_check(&puzzle.gameboard, options.config.clone(), &puzzle.variants) {
+            Ok(()) => println!("puzzle {}: ok", i + 1),
+            Err(divergence) => {
+                println!("puzzle {}: {divergence}", i + 1);
+                println!("{}", io::to_ss(&puzzle.gameboard));
+                divergences += 1;
+            }
+        }
     }
-    fn mark_as_solved(&mut self, x: usize, y: usize) {
-        self.cells[x][y] = 0;
+
+    if divergences > 0 {
+        return Err(std::io::Error::other(format!(
+            "{divergences} puzzles diverged"
+        )));
     }
+
+    Ok(())
 }
 
-trait Rule<const X: usize, const Y: usize> {
-    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>);
+// a snapshot is the final board, a blank line, then `Trace::snapshot`. a missing one is
+// saved, and a changed one fails the run unless --update is given
+fn snapshot(options: &Options) -> std::io::Result<()> {
+    fs::create_dir_all(&options.snapshots)?;
+
+    let mut changed = 0;
+
+    for (puzzle, candidates) in options.puzzles()? {
+        let mut gameboard = puzzle.gameboard.clone();
+        let mut candidates = candidates;
+        let mut trace = Trace::default();
+
+        Solver::new(options.config.clone(), &puzzle.variants).resume(
+            &mut gameboard,
+            &mut candidates,
+            Some(&mut trace),
+        );
+
+        // variants change the solve path, so they're part of the key
+        let fingerprint = format!("{:016x}", puzzle.fingerprint());
+        let path = Path::new(&options.snapshots).join(format!("{fingerprint}.snap"));
+        let actual = format!("{}\n{}", io::to_ss(&gameboard), trace.snapshot());
+
+        let expected = match fs::read_to_string(&path) {
+            Ok(expected) => expected,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                fs::write(&path, actual)?;
+                println!("{fingerprint}: saved");
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+
+        if expected == actual {
+            println!("{fingerprint}: ok");
+            continue;
+        }
+
+        if options.update {
+            fs::write(&path, actual)?;
+            println!("{fingerprint}: updated");
+            continue;
+        }
+
+        changed += 1;
+        println!("{fingerprint}: changed");
+        print_snapshot_diff(&expected, &actual, &gameboard);
+    }
+
+    if changed > 0 {
+        return Err(std::io::Error::other(format!(
+            "{changed} solve paths changed, rerun with --update to accept them"
+        )));
+    }
+
+    Ok(())
 }
 
-struct ExcludeWhenSolved;
+fn print_snapshot_diff(expected: &str, actual: &str, gameboard: &Gameboard<9, 9>) {
+    let (expected_board, expected_steps) = expected.split_once("\n\n").unwrap_or((expected, ""));
+    let (_, actual_steps) = actual.split_once("\n\n").unwrap_or((actual, ""));
 
-impl<const X: usize, const Y: usize> Rule<X, Y> for ExcludeWhenSolved {
-    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
-        for x in 0..X {
-            for y in 0..Y {
-                if gameboard.state[x][y] == 0 {
-                    continue;
-                };
+    if let Ok(expected_board) = io::parse_ss(expected_board) {
+        for ((x, y), was, now) in expected_board.diff(gameboard) {
+            println!("  r{}c{}: {was} -> {now}", x + 1, y + 1);
+        }
+    }
 
-                candidates.mark_as_solved(x, y);
+    let mut expected_steps = expected_steps.lines();
+    let mut actual_steps = actual_steps.lines();
+
+    for step in 1.. {
+        match (expected_steps.next(), actual_steps.next()) {
+            (None, None) => break,
+            (was, now) if was == now => {}
+            (was, now) => {
+                println!(
+                    "  step {step}: {} -> {}",
+                    was.unwrap_or("end"),
+                    now.unwrap_or("end")
+                );
+                break;
             }
         }
     }
 }
 
-#[derive(Clone)]
-struct Region {
-    positions: Rc<Vec<(usize, usize)>>,
+// csv, or json when the output path ends in .json
+fn rate(options: &Options) -> std::io::Result<()> {
+    if options.input.is_none() {
+        return Err(invalid_input("rate requires --input"));
+    }
+
+    // a batch's puzzles usually share their variants, so the solver is only rebuilt
+    // when they change
+    let mut variants = vec![];
+    let mut solver = Solver::reusable(options.config.clone(), &variants);
+
+    let ratings: Vec<Rating> = options
+        .puzzles()?
+        .iter()
+        .map(|(puzzle, _)| {
+            if puzzle.variants != variants {
+                variants.clone_from(&puzzle.variants);
+                solver = Solver::reusable(options.config.clone(), &variants);
+            }
+
+            // keyed like snapshots, by the variants as well as the board
+            let mut rating = rate_with(&mut solver, &puzzle.gameboard);
+            rating.fingerprint = puzzle.fingerprint();
+            rating
+        })
+        .collect();
+
+    match &options.output {
+        Some(path)
+            if Path::new(path)
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("json")) =>
+        {
+            fs::write(path, io::ratings_to_json(&ratings))
+        }
+        Some(path) => fs::write(path, io::ratings_to_csv(&ratings)),
+        None => {
+            print!("{}", io::ratings_to_csv(&ratings));
+            Ok(())
+        }
+    }
 }
 
-impl Region {
-    fn new(positions: Vec<(usize, usize)>) -> Self {
-        Self {
-            positions: Rc::new(positions),
+// keeps the first of each set of puzzles with the same canonical form, and reports
+// the lines of the rest on stderr so the written collection stays clean
+fn dedupe(options: &Options) -> std::io::Result<()> {
+    let Some(input) = &options.input else {
+        return Err(invalid_input("dedupe requires --input"));
+    };
+
+    // numbered the way parse_sdm numbers them, counting blank lines
+    let lines = input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, _)| i + 1);
+
+    let gameboards = io::parse_sdm(input).map_err(invalid_data)?;
+    let total = gameboards.len();
+
+    let mut first_seen = HashMap::new();
+    let mut kept = vec![];
+
+    for (line, gameboard) in lines.zip(gameboards) {
+        match first_seen.entry(io::to_line(&gameboard.canonical())) {
+            Entry::Occupied(first) => {
+                eprintln!("line {line}: duplicate of line {}", first.get());
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(line);
+                kept.push(gameboard);
+            }
         }
     }
+
+    eprintln!("kept {} of {total} puzzles", kept.len());
+
+    if let Some(path) = &options.output {
+        fs::write(path, io::to_sdm(&kept))
+    } else {
+        print!("{}", io::to_sdm(&kept));
+        Ok(())
+    }
 }
 
-struct UniqueByRegion(Rc<Region>);
+fn fuzz(options: &Options, puzzle: &io::Puzzle) -> std::io::Result<()> {
+    let mut solution = puzzle.gameboard.clone();
 
-impl<const X: usize, const Y: usize> Rule<X, Y> for UniqueByRegion {
-    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
-        for (x, y) in self.0.positions.iter() {
-            if gameboard.state[*x][*y] == 0 {
-                continue;
-            };
+    let config = options.config.clone().backend(Backend::Search);
+    Solver::new(config, &[]).solve(&mut solution, None);
 
-            for (x2, y2) in self.0.positions.iter() {
-                if (x2, y2) == (x, y) {
-                    continue;
-                }
+    if !solution.is_solved() {
+        return Err(invalid_input("fuzz-solve needs a puzzle with a solution"));
+    }
 
-                candidates.exclude_candidate(*x2, *y2, gameboard.state[*x][*y]);
-            }
+    match fuzz_solve(
+        &solution,
+        options.config.clone(),
+        options.rounds,
+        options.seed,
+    ) {
+        Ok(()) => {
+            println!("{} rounds passed (seed {})", options.rounds, options.seed);
+            Ok(())
+        }
+        Err(failure) => {
+            println!("{failure} (seed {})", options.seed);
+            println!("{}", io::to_ss(&failure.puzzle));
+            Err(invalid_data(failure))
         }
     }
 }
 
-struct FillRegionUniquely(Rc<Region>);
+fn solve(
+    mut options: Options,
+    mut puzzle: io::Puzzle,
+    mut candidates: Candidates<9, 9>,
+) -> std::io::Result<()> {
+    if options.estimate {
+        println!(
+            "estimated difficulty: {:?}",
+            puzzle.gameboard.estimate_difficulty()
+        );
+    }
 
-impl<const X: usize, const Y: usize> Rule<X, Y> for FillRegionUniquely {
-    fn visit(&self, _: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
-        'next_n: for n in 1..=9 {
-            let mut solo_position = None;
+    let renderers = &options.renderers;
 
-            for (x, y) in self.0.positions.iter() {
-                if candidates.cells[*x][*y] & n.to_cell_mask() > 0 {
-                    if solo_position.is_some() {
-                        continue 'next_n;
-                    }
+    let Some(renderer) = renderers.get(&options.format) else {
+        return Err(invalid_input(format!(
+            "--format requires one of {}",
+            renderers.names().join(", ")
+        )));
+    };
 
-                    solo_position = Some((x, y));
-                }
-            }
+    if let Some(timeout) = options.timeout {
+        let handle = SolveHandle::default();
+        options.config.cancel = Some(handle.clone());
 
-            if let Some((x, y)) = solo_position {
-                for (x2, y2) in self.0.positions.iter() {
-                    if (x2, y2) == (x, y) {
-                        candidates.set_exclusive_candidate(*x2, *y2, n);
-                    } else {
-                        candidates.exclude_candidate(*x2, *y2, n);
-                    }
-                }
-            }
-        }
+        thread::spawn(move || {
+            thread::sleep(timeout);
+            handle.cancel();
+        });
     }
-}
 
-fn main() {
-    let mut gameboard: Gameboard<9, 9> = [
-        [0, 0, 0, 0, 8, 0, 0, 0, 0],
-        [0, 0, 5, 6, 0, 3, 9, 0, 0],
-        [0, 8, 4, 0, 0, 0, 2, 7, 0],
-        [0, 3, 0, 1, 0, 0, 0, 5, 0],
-        [5, 0, 0, 0, 3, 0, 0, 0, 2],
-        [0, 6, 0, 0, 0, 5, 0, 1, 0],
-        [0, 1, 9, 0, 0, 0, 5, 6, 0],
-        [0, 0, 8, 4, 0, 2, 7, 0, 0],
-        [0, 0, 0, 0, 6, 0, 0, 0, 0],
-    ]
-    .into();
-
-    let rules = build_9x9_rules();
-
-    let mut candidates = Candidates::<9, 9>::default();
-
-    loop {
-        for rule in &rules {
-            rule.visit(&gameboard, &mut candidates);
-        }
+    let solver = Solver::new(options.config.clone(), &puzzle.variants);
+    // taken before the solve fills the board in
+    let fingerprint = puzzle.fingerprint();
+
+    if let Some(technique) = &options.config.stop_at {
+        let techniques = solver.techniques();
 
-        if !candidates.apply_uniques(&mut gameboard) {
-            break;
+        if !techniques.contains(&technique.as_str()) {
+            return Err(invalid_input(format!(
+                "--stop-at requires one of {}",
+                techniques.join(", ")
+            )));
         }
     }
 
-    println!("{gameboard}");
-    println!("{candidates:?}");
-}
+    // kept for the highlights, alongside the history if there is one
+    let mut trace = Trace::default();
 
-fn build_9x9_rules() -> Vec<Box<dyn Rule<9, 9>>> {
-    let mut rules: Vec<Box<dyn Rule<9, 9>>> = vec![];
+    let stats = match options.history.as_mut() {
+        Some(history) => solver.resume(
+            &mut puzzle.gameboard,
+            &mut candidates,
+            Some(&mut Tee(history, &mut trace)),
+        ),
+        None => solver.resume(&mut puzzle.gameboard, &mut candidates, Some(&mut trace)),
+    };
 
-    let regions: Vec<Rc<Region>> = build_9x9_regions()
-        .iter()
-        .cloned()
-        .map(Region::new)
-        .map(Rc::new)
-        .collect();
+    if let Some(history) = options.history {
+        history.finish()?;
+    }
 
-    rules.push(Box::new(ExcludeWhenSolved {}));
+    if stats.cancelled {
+        eprintln!("cancelled after {:?}", stats.elapsed);
+    }
 
-    for region in &regions {
-        rules.push(Box::new(UniqueByRegion(region.clone())));
+    if let Some(path) = &options.save_state {
+        fs::write(path, io::export_state(&puzzle, &candidates))?;
     }
 
-    for region in &regions {
-        rules.push(Box::new(FillRegionUniquely(region.clone())));
+    let gameboard = &puzzle.gameboard;
+
+    if let Some(technique) = &options.config.stop_at {
+        print_stop(technique, &stats, gameboard, &candidates);
     }
 
-    rules
-}
+    let mut render_options = RenderOptions::default();
+    render_options.fingerprint = Some(fingerprint);
+
+    if options.highlight {
+        render_options.recent = trace.last_placements();
+        render_options.conflicts = true;
+        // escapes would end up in the file, or in whatever stdout is piped to
+        render_options.escapes = options.output.is_none() && std::io::stdout().is_terminal();
+    }
 
-fn build_9x9_regions() -> Vec<Vec<(usize, usize)>> {
-    let mut regions: Vec<Vec<(usize, usize)>> = vec![];
+    let output = renderer.render(gameboard, &candidates, &render_options);
 
-    // rows
-    for x in 0..9 {
-        regions.push((0..9).map(|y| (x, y)).collect());
+    if let Some(path) = &options.output {
+        fs::write(path, output)?;
+    } else {
+        println!("{output}");
+
+        // the board-only formats are followed by the raw candidates
+        if matches!(renderer.name(), "plain" | "ss") {
+            println!("{candidates:?}");
+        }
     }
 
-    // columns
-    for y in 0..9 {
-        regions.push((0..9).map(|x| (x, y)).collect());
+    if options.print_stats {
+        print!("{stats}");
     }
 
-    // 3x3 boxes
-    for x_outer in 0..3 {
-        for y_outer in 0..3 {
-            regions.push(
-                (0..3)
-                    .flat_map(|x_inner| {
-                        (0..3).map(move |y_inner| (x_outer * 3 + x_inner, y_outer * 3 + y_inner))
-                    })
-                    .collect(),
-            );
-        }
+    Ok(())
+}
+
+fn print_stop(
+    technique: &str,
+    stats: &Stats,
+    gameboard: &Gameboard<9, 9>,
+    candidates: &Candidates<9, 9>,
+) {
+    let Some(stop) = &stats.stopped else {
+        println!("{technique} was never needed\n");
+        return;
+    };
+
+    println!("stopped before {}: {}\n", stop.technique, stop.explanation);
+
+    let mut highlights = stop.eliminations.clone();
+    highlights.extend(
+        stop.placements
+            .iter()
+            .map(|(position, _)| (*position, candidates.candidates_at(*position))),
+    );
+
+    println!("{}", io::to_pencilmarks(gameboard, candidates, &highlights));
+
+    for ((x, y), digits) in &stop.eliminations {
+        let digits: Vec<String> = digits.iter().map(|n| n.to_string()).collect();
+        println!("r{}c{} loses {}", x + 1, y + 1, digits.join(", "));
+    }
+
+    for ((x, y), n) in &stop.placements {
+        println!("r{}c{} is {n}", x + 1, y + 1);
     }
 
-    regions
+    println!();
+}
+
+fn or_none(list: &str) -> &str {
+    if list.is_empty() {
+        "none"
+    } else {
+        list
+    }
+}
+
+// the argument after `flag`, or an error saying what the flag expects
+fn value<T>(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+    expected: &str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> std::io::Result<T> {
+    args.next()
+        .as_deref()
+        .and_then(parse)
+        .ok_or_else(|| invalid_input(format!("{flag} requires {expected}")))
+}
+
+fn invalid_input(message: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, message.into())
+}
+
+fn invalid_data(error: impl std::error::Error + Send + Sync + 'static) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
 }
@@ -0,0 +1,605 @@
+use std::rc::Rc;
+
+use crate::{candidates::ToCellMask, CandidateSet, Candidates, Gameboard, Rule};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Row,
+    Column,
+    Box,
+}
+
+impl Kind {
+    // `build_9x9_regions` lists the rows, then the columns, then the boxes
+    fn of(region: usize) -> Self {
+        match region / 9 {
+            0 => Self::Row,
+            1 => Self::Column,
+            _ => Self::Box,
+        }
+    }
+
+    fn mask(self) -> u32 {
+        match self {
+            Self::Row => 0x1ff,
+            Self::Column => 0x1ff << 9,
+            Self::Box => 0x1ff << 18,
+        }
+    }
+}
+
+// the regions the pattern rules reason over, with the regions each cell is in as
+// bits so two cells can be checked for seeing each other at a glance, and the cells
+// each cell sees as bits of a whole board
+pub(crate) struct Regions {
+    cells: Vec<Vec<(usize, usize)>>,
+    membership: [[u32; 9]; 9],
+    peers: [[u128; 9]; 9],
+}
+
+// a cell's bit in a whole-board mask
+fn bit((x, y): (usize, usize)) -> u128 {
+    1 << (x * 9 + y)
+}
+
+impl Regions {
+    pub(crate) fn new(regions: &[Vec<(usize, usize)>]) -> Self {
+        let mut membership = [[0; 9]; 9];
+
+        for (i, region) in regions.iter().enumerate() {
+            for (x, y) in region {
+                membership[*x][*y] |= 1 << i;
+            }
+        }
+
+        let mut peers = [[0; 9]; 9];
+
+        for region in regions {
+            let cells = region
+                .iter()
+                .fold(0, |cells, position| cells | bit(*position));
+
+            for (x, y) in region {
+                peers[*x][*y] |= cells & !bit((*x, *y));
+            }
+        }
+
+        Self {
+            cells: regions.to_vec(),
+            membership,
+            peers,
+        }
+    }
+
+    fn shared(&self, (x, y): (usize, usize), (x2, y2): (usize, usize)) -> u32 {
+        self.membership[x][y] & self.membership[x2][y2]
+    }
+
+    fn sees(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        a != b && self.shared(a, b) != 0
+    }
+
+    fn shares(&self, a: (usize, usize), b: (usize, usize), kind: Kind) -> bool {
+        self.shared(a, b) & kind.mask() != 0
+    }
+
+    fn contains(&self, region: usize, (x, y): (usize, usize)) -> bool {
+        self.membership[x][y] & 1 << region != 0
+    }
+
+    // the cells of a region that can still take `digit`, or none if it's placed there. a
+    // region that already has the digit placed may not have been cleared of it yet
+    fn digit_cells(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        candidates: &Candidates<9, 9>,
+        digit: u8,
+        region: usize,
+    ) -> Option<Vec<(usize, usize)>> {
+        let region = &self.cells[region];
+
+        if region.iter().any(|(x, y)| gameboard.cell(*x, *y) == digit) {
+            return None;
+        }
+
+        Some(
+            region
+                .iter()
+                .copied()
+                .filter(|(x, y)| candidates.cells[*x][*y] & digit.to_cell_mask() > 0)
+                .collect(),
+        )
+    }
+
+    // regions where `digit` is left in exactly two cells, so one of them holds it
+    fn strong_links(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        candidates: &Candidates<9, 9>,
+        digit: u8,
+    ) -> Vec<(Kind, [(usize, usize); 2])> {
+        (0..self.cells.len())
+            .filter_map(
+                |i| match self.digit_cells(gameboard, candidates, digit, i)?[..] {
+                    [a, b] => Some((Kind::of(i), [a, b])),
+                    _ => None,
+                },
+            )
+            .collect()
+    }
+
+    // removes `digit` from every cell other than `except` that sees both `a` and `b`
+    fn eliminate_seen_by(
+        &self,
+        candidates: &mut Candidates<9, 9>,
+        digit: u8,
+        (a, b): ((usize, usize), (usize, usize)),
+        except: &[(usize, usize)],
+    ) {
+        for x in 0..9 {
+            for y in 0..9 {
+                if !except.contains(&(x, y)) && self.sees((x, y), a) && self.sees((x, y), b) {
+                    candidates.exclude_candidate(x, y, digit);
+                }
+            }
+        }
+    }
+}
+
+// two strong links on one digit joined by a weak one, so one of the far ends holds
+// the digit and nothing seeing both can. the patterns only differ in the regions
+// involved, and each chain goes to exactly one of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ChainPattern {
+    // two rows, or two columns, whose near ends share a column, or a row
+    Skyscraper,
+    // a row and a column whose near ends share a box
+    TwoStringKite,
+    // any other way of joining two strong links
+    TurbotFish,
+}
+
+impl ChainPattern {
+    fn classify(
+        regions: &Regions,
+        (first, second): (Kind, Kind),
+        (b, c): ((usize, usize), (usize, usize)),
+    ) -> Self {
+        match (first, second) {
+            (Kind::Row, Kind::Row) if regions.shares(b, c, Kind::Column) => Self::Skyscraper,
+            (Kind::Column, Kind::Column) if regions.shares(b, c, Kind::Row) => Self::Skyscraper,
+            (Kind::Row, Kind::Column) | (Kind::Column, Kind::Row)
+                if regions.shares(b, c, Kind::Box) =>
+            {
+                Self::TwoStringKite
+            }
+            _ => Self::TurbotFish,
+        }
+    }
+}
+
+pub(crate) struct SingleDigitChain {
+    pattern: ChainPattern,
+    regions: Rc<Regions>,
+}
+
+impl SingleDigitChain {
+    pub(crate) fn new(pattern: ChainPattern, regions: Rc<Regions>) -> Self {
+        Self { pattern, regions }
+    }
+}
+
+impl Rule<9, 9> for SingleDigitChain {
+    fn name(&self) -> &'static str {
+        match self.pattern {
+            ChainPattern::Skyscraper => "skyscraper",
+            ChainPattern::TwoStringKite => "two_string_kite",
+            ChainPattern::TurbotFish => "turbot_fish",
+        }
+    }
+
+    fn explanation(&self) -> &'static str {
+        match self.pattern {
+            ChainPattern::Skyscraper => "two rows or columns each have a digit in two places, lined up at one end, so one of the other ends holds it and cells seeing both lose it",
+            ChainPattern::TwoStringKite => "a row and a column each have a digit in two places, with an end of each in the same box, so one of the other ends holds it and cells seeing both lose it",
+            ChainPattern::TurbotFish => "two regions each have a digit in two places, with an end of one seeing an end of the other, so one of the far ends holds it and cells seeing both lose it",
+        }
+    }
+
+    fn cost(&self) -> u32 {
+        4
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        None
+    }
+
+    fn is_satisfied(&self, _: &Gameboard<9, 9>) -> bool {
+        true
+    }
+
+    fn visit(&self, gameboard: &Gameboard<9, 9>, candidates: &mut Candidates<9, 9>) {
+        let regions = &self.regions;
+
+        for digit in 1..=9_u8 {
+            let links = regions.strong_links(gameboard, candidates, digit);
+
+            for (i, (first_kind, first)) in links.iter().enumerate() {
+                for (second_kind, second) in &links[i + 1..] {
+                    for [a, b] in [*first, [first[1], first[0]]] {
+                        for [c, d] in [*second, [second[1], second[0]]] {
+                            if [a, b].contains(&c) || [a, b].contains(&d) || !regions.sees(b, c) {
+                                continue;
+                            }
+
+                            let pattern = ChainPattern::classify(
+                                regions,
+                                (*first_kind, *second_kind),
+                                (b, c),
+                            );
+
+                            if pattern == self.pattern {
+                                regions.eliminate_seen_by(candidates, digit, (a, d), &[a, b, c, d]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// a box whose candidates for a digit all lie on the cross of one row and one column
+// through it, the pivot, without being on just one of them. whichever arm holds the
+// digit, a strong link leaving the box from one arm's line rules it out where the
+// link's far end lines up with the other arm
+pub(crate) struct EmptyRectangle {
+    regions: Rc<Regions>,
+}
+
+impl EmptyRectangle {
+    pub(crate) fn new(regions: Rc<Regions>) -> Self {
+        Self { regions }
+    }
+
+    // the row and column of each pivot the box's cells can be crossed at
+    fn pivots(cells: &[(usize, usize)]) -> Vec<(usize, usize)> {
+        let mut pivots: Vec<(usize, usize)> = cells
+            .iter()
+            .flat_map(|(x, _)| cells.iter().map(move |(_, y)| (*x, *y)))
+            .filter(|(r, c)| {
+                cells.iter().all(|(x, y)| x == r || y == c)
+                    && cells.iter().any(|(x, _)| x != r)
+                    && cells.iter().any(|(_, y)| y != c)
+            })
+            .collect();
+
+        pivots.sort_unstable();
+        pivots.dedup();
+        pivots
+    }
+}
+
+impl Rule<9, 9> for EmptyRectangle {
+    fn name(&self) -> &'static str {
+        "empty_rectangle"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "a box has a digit only on the row and column through one pivot cell, so a row or column with the digit in two places, one of them on the pivot's column or row, rules it out where its other end crosses the pivot's row or column"
+    }
+
+    fn cost(&self) -> u32 {
+        4
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        None
+    }
+
+    fn is_satisfied(&self, _: &Gameboard<9, 9>) -> bool {
+        true
+    }
+
+    fn visit(&self, gameboard: &Gameboard<9, 9>, candidates: &mut Candidates<9, 9>) {
+        let regions = &self.regions;
+
+        for digit in 1..=9_u8 {
+            let links = regions.strong_links(gameboard, candidates, digit);
+
+            for region in (0..regions.cells.len()).filter(|i| Kind::of(*i) == Kind::Box) {
+                let Some(cells) = regions.digit_cells(gameboard, candidates, digit, region) else {
+                    continue;
+                };
+
+                for (r, c) in Self::pivots(&cells) {
+                    for (kind, link) in &links {
+                        for [q, p] in [*link, [link[1], link[0]]] {
+                            // a column's end on the pivot's row empties the row's arm,
+                            // leaving the column's arm, and the other way around
+                            let target = match kind {
+                                Kind::Column if q.0 == r => (p.0, c),
+                                Kind::Row if q.1 == c => (r, p.1),
+                                _ => continue,
+                            };
+
+                            if regions.contains(region, q)
+                                || regions.contains(region, target)
+                                || target == p
+                                || !candidates.candidates_at(target).contains(digit)
+                            {
+                                continue;
+                            }
+
+                            candidates.exclude_candidate(target.0, target.1, digit);
+                            candidates.add_detail(&format!(
+                                "box {}, pivot r{}c{}",
+                                region - 17,
+                                r + 1,
+                                c + 1
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// n empty cells of one region between them holding n + 1 digits, so placing any one
+// of those digits elsewhere locks the rest into the cells
+struct AlmostLockedSet {
+    cells: u128,
+    digits: CandidateSet,
+    // by digit, the cells of the set holding it
+    holding: [u128; 9],
+    // by digit, the cells seeing every cell of the set holding it
+    seen: [u128; 9],
+}
+
+impl AlmostLockedSet {
+    fn new(regions: &Regions, candidates: &Candidates<9, 9>, cells: &[(usize, usize)]) -> Self {
+        let mut set = Self {
+            cells: 0,
+            digits: CandidateSet::default(),
+            holding: [0; 9],
+            seen: [u128::MAX; 9],
+        };
+
+        for (x, y) in cells {
+            let digits = candidates.candidates_at((*x, *y));
+
+            set.cells |= bit((*x, *y));
+            set.digits = set.digits | digits;
+
+            for digit in digits.iter() {
+                set.holding[usize::from(digit - 1)] |= bit((*x, *y));
+                set.seen[usize::from(digit - 1)] &= regions.peers[*x][*y];
+            }
+        }
+
+        set
+    }
+}
+
+// every almost locked set in any region, once each even when it fits in two regions
+fn almost_locked_sets(
+    regions: &Regions,
+    gameboard: &Gameboard<9, 9>,
+    candidates: &Candidates<9, 9>,
+) -> Vec<AlmostLockedSet> {
+    let mut sets: Vec<AlmostLockedSet> = regions
+        .cells
+        .iter()
+        .flat_map(|region| {
+            let empty: Vec<(usize, usize)> = region
+                .iter()
+                .copied()
+                .filter(|(x, y)| gameboard.cell(*x, *y) == 0 && candidates.cells[*x][*y] != 0)
+                .collect();
+
+            (1..1_u32 << empty.len()).filter_map(move |subset| {
+                let cells: Vec<(usize, usize)> = empty
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| subset & 1 << i != 0)
+                    .map(|(_, position)| *position)
+                    .collect();
+
+                let set = AlmostLockedSet::new(regions, candidates, &cells);
+
+                (set.digits.len() == set.cells.count_ones() + 1).then_some(set)
+            })
+        })
+        .collect();
+
+    sets.sort_unstable_by_key(|set| set.cells);
+    sets.dedup_by_key(|set| set.cells);
+    sets
+}
+
+// two almost locked sets sharing a digit x whose cells in one all see its cells in
+// the other, so at most one set holds x and the other is locked. any other shared
+// digit z is then in one set or the other, and cells seeing every z in both lose it
+pub(crate) struct AlsXz {
+    regions: Rc<Regions>,
+}
+
+impl AlsXz {
+    pub(crate) fn new(regions: Rc<Regions>) -> Self {
+        Self { regions }
+    }
+
+    // the rule's eliminations on the candidates as they were when it started, so
+    // clearing one doesn't break up a set another pair still relies on
+    fn eliminations(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        candidates: &Candidates<9, 9>,
+    ) -> Vec<((usize, usize), u8)> {
+        let sets = almost_locked_sets(&self.regions, gameboard, candidates);
+
+        let mut holding = [0_u128; 9];
+
+        for (x, y) in (0..9).flat_map(|x| (0..9).map(move |y| (x, y))) {
+            for digit in candidates.candidates_at((x, y)).iter() {
+                holding[usize::from(digit - 1)] |= bit((x, y));
+            }
+        }
+
+        let mut eliminated = [0_u128; 9];
+
+        for (i, a) in sets.iter().enumerate() {
+            for b in &sets[i + 1..] {
+                if a.cells & b.cells != 0 {
+                    continue;
+                }
+
+                let common = a.digits & b.digits;
+
+                let restricted = common.iter().filter(|x| {
+                    let x = usize::from(x - 1);
+                    a.holding[x] & !b.seen[x] == 0
+                });
+
+                for x in restricted {
+                    for z in (common - CandidateSet(x.to_cell_mask())).iter() {
+                        let z = usize::from(z - 1);
+                        eliminated[z] |= a.seen[z] & b.seen[z] & holding[z];
+                    }
+                }
+            }
+        }
+
+        (0..9)
+            .flat_map(|x| (0..9).map(move |y| (x, y)))
+            .flat_map(|position| {
+                (1..=9_u8)
+                    .filter(move |digit| eliminated[usize::from(digit - 1)] & bit(position) != 0)
+                    .map(move |digit| (position, digit))
+            })
+            .collect()
+    }
+}
+
+impl Rule<9, 9> for AlsXz {
+    fn name(&self) -> &'static str {
+        "als_xz"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "two groups of cells each have one more digit than cells, and a digit they share can only go in one of them, so another shared digit must be in one group or the other and cells seeing all of its places lose it"
+    }
+
+    fn cost(&self) -> u32 {
+        5
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        None
+    }
+
+    fn is_satisfied(&self, _: &Gameboard<9, 9>) -> bool {
+        true
+    }
+
+    fn visit(&self, gameboard: &Gameboard<9, 9>, candidates: &mut Candidates<9, 9>) {
+        for ((x, y), digit) in self.eliminations(gameboard, candidates) {
+            candidates.exclude_candidate(x, y, digit);
+        }
+    }
+}
+
+// the pattern rules over `regions`, rows then columns then boxes
+pub(crate) fn pattern_rules(regions: &[Vec<(usize, usize)>]) -> Vec<Box<dyn Rule<9, 9>>> {
+    let regions = Rc::new(Regions::new(regions));
+
+    [
+        ChainPattern::Skyscraper,
+        ChainPattern::TwoStringKite,
+        ChainPattern::TurbotFish,
+    ]
+    .into_iter()
+    .map(|pattern| -> Box<dyn Rule<9, 9>> {
+        Box::new(SingleDigitChain::new(pattern, regions.clone()))
+    })
+    .chain([
+        Box::new(EmptyRectangle::new(regions.clone())) as Box<dyn Rule<9, 9>>,
+        Box::new(AlsXz::new(regions.clone())),
+    ])
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        build_9x9_regions, io, rules::rules_for_regions, Backend, Engine, Solver, SolverConfig,
+    };
+
+    // what `name` removes once the region rules stall on `puzzle`, each checked against
+    // the solution
+    fn eliminations(puzzle: &str, name: &str) -> Vec<((usize, usize), u8)> {
+        let mut gameboard = io::parse_ss(puzzle).unwrap();
+        let mut solution = gameboard.clone();
+        let search = SolverConfig {
+            backend: Backend::Search,
+            ..SolverConfig::default()
+        };
+        Solver::new(search, &[]).solve(&mut solution, None);
+        assert!(solution.is_solved());
+
+        let mut candidates = Candidates::default();
+        Engine::new(
+            rules_for_regions(&build_9x9_regions()),
+            SolverConfig::default(),
+        )
+        .run(&mut gameboard, &mut candidates, None);
+
+        let before = candidates.clone();
+        let rules = pattern_rules(&build_9x9_regions());
+        let rule = rules.iter().find(|rule| rule.name() == name).unwrap();
+        rule.visit(&gameboard, &mut candidates);
+
+        let removed: Vec<((usize, usize), u8)> = (0..9)
+            .flat_map(|x| (0..9).map(move |y| (x, y)))
+            .flat_map(|position| {
+                (before.candidates_at(position) - candidates.candidates_at(position))
+                    .iter()
+                    .map(move |digit| (position, digit))
+            })
+            .collect();
+
+        for ((x, y), digit) in &removed {
+            assert_ne!(
+                solution.cell(*x, *y),
+                *digit,
+                "{name} removed r{}c{}",
+                x + 1,
+                y + 1
+            );
+        }
+
+        removed
+    }
+
+    #[test]
+    fn chains_only_remove_digits_the_solution_leaves_out() {
+        let skyscraper =
+            "....5..398...2..4.9...782..2.9...4.......7..6.4......81..........73.6....86.....7";
+        let kite =
+            "..97...32.5.2...4......9........8....4.59..1..8...1..5..3....6.6..98......7.2....";
+
+        assert_eq!(eliminations(skyscraper, "skyscraper").len(), 2);
+        assert_eq!(eliminations(kite, "two_string_kite").len(), 1);
+        assert_eq!(eliminations(kite, "turbot_fish").len(), 1);
+    }
+
+    #[test]
+    fn als_xz_only_removes_digits_the_solution_leaves_out() {
+        let puzzle =
+            ".....541...18......95....62.7...49......7....3..6.....1.84..7.5.64.5..31....1....";
+
+        assert_eq!(eliminations(puzzle, "als_xz").len(), 18);
+    }
+}
@@ -0,0 +1,13 @@
+// what most users need, behind one `use rust_sudoku_solver::prelude::*`. everything
+// here follows semver: nothing is removed or changed incompatibly before 2.0. the
+// enums and the config, result and technique structs are `#[non_exhaustive]`, so
+// variants, settings and fields can still be added in minor releases. they're built
+// from `Default` or `From` instead, e.g. a classic `Puzzle` from a `Gameboard`
+pub use crate::{
+    io::{
+        detect_format, parse_detected, parse_extended, parse_sdk, parse_sdm, parse_ss, Format,
+        ParseError, Puzzle,
+    },
+    solve_str, Backend, Gameboard, SolveResult, Solver, SolverConfig, Stats, Strategy, SudokuError,
+    Technique,
+};
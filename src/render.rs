@@ -0,0 +1,575 @@
+use crate::{io, Candidates, Gameboard};
+
+pub trait Renderer {
+    // the format name it's registered and picked under
+    fn name(&self) -> &'static str;
+    // formats without a way to mark cells ignore `options`
+    fn render(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        candidates: &Candidates<9, 9>,
+        options: &RenderOptions,
+    ) -> String;
+}
+
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RenderOptions {
+    // cells to pick out as just placed, e.g. from `Trace::last_placements`
+    pub recent: Vec<(usize, usize)>,
+    // show digits repeated in their row, column or box in red
+    pub conflicts: bool,
+    // lets the terminal formats use escape codes for the marks. off by default, since
+    // they're noise anywhere but a terminal
+    pub escapes: bool,
+    // the puzzle's id for the formats that print one, since the board they're given is
+    // usually solved by then. the board's own fingerprint if it's not set
+    pub fingerprint: Option<u64>,
+}
+
+#[derive(Clone, Copy)]
+struct Mark {
+    recent: bool,
+    conflict: bool,
+}
+
+struct Marks<'a> {
+    recent: &'a [(usize, usize)],
+    conflicts: Vec<(usize, usize)>,
+    escapes: bool,
+}
+
+impl Marks<'_> {
+    fn at(&self, position: (usize, usize)) -> Mark {
+        Mark {
+            recent: self.recent.contains(&position),
+            conflict: self.conflicts.contains(&position),
+        }
+    }
+}
+
+impl RenderOptions {
+    fn marks(&self, gameboard: &Gameboard<9, 9>) -> Marks<'_> {
+        Marks {
+            recent: &self.recent,
+            escapes: self.escapes,
+            conflicts: if self.conflicts {
+                gameboard.conflicts()
+            } else {
+                vec![]
+            },
+        }
+    }
+}
+
+impl Marks<'_> {
+    // bold for recent, red for conflicting, as terminal escapes if they're allowed
+    fn ansi(&self, position: (usize, usize), text: &str) -> String {
+        let mark = self.at(position);
+
+        if !self.escapes {
+            return text.to_string();
+        }
+
+        match (mark.recent, mark.conflict) {
+            (false, false) => text.to_string(),
+            (true, false) => format!("\x1b[1m{text}\x1b[0m"),
+            (false, true) => format!("\x1b[31m{text}\x1b[0m"),
+            (true, true) => format!("\x1b[1;31m{text}\x1b[0m"),
+        }
+    }
+}
+
+// renderers by format name, starting with the built-in ones
+pub struct Renderers {
+    renderers: Vec<Box<dyn Renderer>>,
+}
+
+impl Default for Renderers {
+    fn default() -> Self {
+        Self {
+            renderers: vec![
+                Box::new(Plain),
+                Box::new(Pretty::default()),
+                Box::new(SimpleSudoku),
+                Box::new(Pencilmarks),
+                Box::new(Json),
+                Box::new(Svg),
+                Box::new(Latex),
+            ],
+        }
+    }
+}
+
+impl Renderers {
+    // replaces any renderer already registered under the same name
+    pub fn register(&mut self, renderer: Box<dyn Renderer>) {
+        self.renderers
+            .retain(|other| other.name() != renderer.name());
+        self.renderers.push(renderer);
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&dyn Renderer> {
+        self.renderers
+            .iter()
+            .find(|renderer| renderer.name() == name)
+            .map(AsRef::as_ref)
+    }
+
+    #[must_use]
+    pub fn names(&self) -> Vec<&'static str> {
+        self.renderers
+            .iter()
+            .map(|renderer| renderer.name())
+            .collect()
+    }
+}
+
+// laid out like the board's Display, with `.` for empty cells
+pub struct Plain;
+
+impl Renderer for Plain {
+    fn name(&self) -> &'static str {
+        "plain"
+    }
+
+    fn render(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        _: &Candidates<9, 9>,
+        options: &RenderOptions,
+    ) -> String {
+        let marks = options.marks(gameboard);
+        let mut output = String::new();
+
+        for x in 0..9 {
+            for (y, cell) in gameboard.row(x).iter().enumerate() {
+                let text = match cell {
+                    0 => ".".to_string(),
+                    n => n.to_string(),
+                };
+
+                output.push_str(&marks.ansi((x, y), &text));
+                output.push(' ');
+            }
+
+            output.push('\n');
+        }
+
+        output
+    }
+}
+
+// the board boxed in with line-drawing characters, optionally followed by how many
+// of each digit are left to place, for following a solve along by hand. like the
+// pencilmarks, a stuck solve shows `!` on empty cells with no candidates left and `*`
+// on ones down to a single that hasn't been placed
+#[derive(Default)]
+pub struct Pretty {
+    pub digit_counts: bool,
+}
+
+impl Renderer for Pretty {
+    fn name(&self) -> &'static str {
+        "pretty"
+    }
+
+    fn render(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        candidates: &Candidates<9, 9>,
+        options: &RenderOptions,
+    ) -> String {
+        let marks = options.marks(gameboard);
+        let exhausted = candidates.exhausted_cells(gameboard);
+        let pending = candidates.pending_singles(gameboard);
+
+        let border = |left: char, middle: char, right: char| {
+            let stack = "\u{2500}".repeat(7);
+            format!("{left}{stack}{middle}{stack}{middle}{stack}{right}\n")
+        };
+
+        let mut output = border('\u{250c}', '\u{252c}', '\u{2510}');
+
+        for x in 0..9 {
+            if x > 0 && x % 3 == 0 {
+                output.push_str(&border('\u{251c}', '\u{253c}', '\u{2524}'));
+            }
+
+            for (y, cell) in gameboard.row(x).iter().enumerate() {
+                if y % 3 == 0 {
+                    output.push_str("\u{2502} ");
+                }
+
+                let text = match cell {
+                    0 if exhausted.contains(&(x, y)) => "!".to_string(),
+                    0 if pending.contains(&(x, y)) => "*".to_string(),
+                    0 => ".".to_string(),
+                    n => n.to_string(),
+                };
+
+                output.push_str(&marks.ansi((x, y), &text));
+                output.push(' ');
+            }
+
+            output.push_str("\u{2502}\n");
+        }
+
+        output.push_str(&border('\u{2514}', '\u{2534}', '\u{2518}'));
+
+        if !exhausted.is_empty() || !pending.is_empty() {
+            output.push_str("! no candidates left, * single not yet placed\n");
+        }
+
+        if self.digit_counts {
+            let remaining = gameboard.remaining_digits();
+
+            let to_place: Vec<String> = (1..=9)
+                .zip(remaining)
+                .filter(|(_, count)| *count > 0)
+                .map(|(n, count)| format!("{n}:{count}"))
+                .collect();
+            let complete: Vec<String> = (1..=9)
+                .zip(remaining)
+                .filter(|(_, count)| *count == 0)
+                .map(|(n, _)| n.to_string())
+                .collect();
+
+            let line = |label: &str, items: &[String], separator: &str| match items {
+                [] => format!("{label}: none\n"),
+                items => format!("{label}: {}\n", items.join(separator)),
+            };
+
+            output.push_str(&line("to place", &to_place, " "));
+            output.push_str(&line("complete", &complete, ", "));
+        }
+
+        output
+    }
+}
+
+pub struct SimpleSudoku;
+
+impl Renderer for SimpleSudoku {
+    fn name(&self) -> &'static str {
+        "ss"
+    }
+
+    fn render(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        _: &Candidates<9, 9>,
+        _: &RenderOptions,
+    ) -> String {
+        io::to_ss(gameboard)
+    }
+}
+
+// the candidates grid, followed by the cells where a stuck solve went wrong
+pub struct Pencilmarks;
+
+impl Renderer for Pencilmarks {
+    fn name(&self) -> &'static str {
+        "pencilmarks"
+    }
+
+    fn render(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        candidates: &Candidates<9, 9>,
+        _: &RenderOptions,
+    ) -> String {
+        let cells = |positions: Vec<(usize, usize)>| -> Vec<String> {
+            positions
+                .iter()
+                .map(|(x, y)| format!("r{}c{}", x + 1, y + 1))
+                .collect()
+        };
+
+        let exhausted = cells(candidates.exhausted_cells(gameboard));
+        let pending = cells(candidates.pending_singles(gameboard));
+
+        let mut lines = vec![io::to_pencilmarks(gameboard, candidates, &[])];
+
+        if !exhausted.is_empty() {
+            lines.push(format!("no candidates left: {}", exhausted.join(", ")));
+        }
+
+        if !pending.is_empty() {
+            lines.push(format!("singles not yet placed: {}", pending.join(", ")));
+        }
+
+        lines.join("\n")
+    }
+}
+
+// `{"fingerprint":"...","board":[[...],...],"candidates":[[[1,2],...],...]}`, like a
+// history step with the puzzle's id in front
+pub struct Json;
+
+impl Renderer for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn render(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        candidates: &Candidates<9, 9>,
+        options: &RenderOptions,
+    ) -> String {
+        let board: Vec<String> = (0..9)
+            .map(|x| {
+                let row: Vec<String> = gameboard.row(x).iter().map(u8::to_string).collect();
+                format!("[{}]", row.join(","))
+            })
+            .collect();
+
+        let candidates: Vec<String> = (0..9)
+            .map(|x| {
+                let row: Vec<String> = (0..9)
+                    .map(|y| {
+                        let digits: Vec<String> = candidates
+                            .candidates_at((x, y))
+                            .iter()
+                            .map(|n| n.to_string())
+                            .collect();
+                        format!("[{}]", digits.join(","))
+                    })
+                    .collect();
+                format!("[{}]", row.join(","))
+            })
+            .collect();
+
+        format!(
+            "{{\"fingerprint\":\"{:016x}\",\"board\":[{}],\"candidates\":[{}]}}\n",
+            options
+                .fingerprint
+                .unwrap_or_else(|| gameboard.fingerprint()),
+            board.join(","),
+            candidates.join(",")
+        )
+    }
+}
+
+// 50px cells, with an empty cell's candidates in small print at their keypad spots
+pub struct Svg;
+
+impl Renderer for Svg {
+    fn name(&self) -> &'static str {
+        "svg"
+    }
+
+    fn render(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        candidates: &Candidates<9, 9>,
+        options: &RenderOptions,
+    ) -> String {
+        let mut lines = vec![
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="460" height="460" viewBox="-5 -5 460 460">"#.to_string(),
+            r#"<rect x="0" y="0" width="450" height="450" fill="white"/>"#.to_string(),
+        ];
+
+        for i in 0..=9 {
+            let width = if i % 3 == 0 { 3 } else { 1 };
+            let at = i * 50;
+
+            lines.push(format!(
+                r#"<line x1="{at}" y1="0" x2="{at}" y2="450" stroke="black" stroke-width="{width}"/>"#
+            ));
+            lines.push(format!(
+                r#"<line x1="0" y1="{at}" x2="450" y2="{at}" stroke="black" stroke-width="{width}"/>"#
+            ));
+        }
+
+        let marks = options.marks(gameboard);
+
+        for x in 0..9 {
+            for y in 0..9 {
+                let (left, top) = (y * 50, x * 50);
+
+                match gameboard.cell(x, y) {
+                    0 => {
+                        for n in candidates.candidates_at((x, y)).iter() {
+                            let (row, column) = (usize::from(n - 1) / 3, usize::from(n - 1) % 3);
+
+                            lines.push(format!(
+                                r#"<text x="{}" y="{}" font-size="12" text-anchor="middle">{n}</text>"#,
+                                left + 9 + column * 16,
+                                top + 15 + row * 16
+                            ));
+                        }
+                    }
+                    n => {
+                        let fill = match marks.at((x, y)) {
+                            Mark { conflict: true, .. } => "red",
+                            Mark { recent: true, .. } => "blue",
+                            Mark { .. } => "black",
+                        };
+
+                        lines.push(format!(
+                            r#"<text x="{}" y="{}" font-size="32" text-anchor="middle" fill="{fill}">{n}</text>"#,
+                            left + 25,
+                            top + 36
+                        ));
+                    }
+                }
+            }
+        }
+
+        lines.push("</svg>".to_string());
+
+        lines.join("\n") + "\n"
+    }
+}
+
+// a tabular with rules between bands and stacks, for dropping into a document
+pub struct Latex;
+
+impl Renderer for Latex {
+    fn name(&self) -> &'static str {
+        "latex"
+    }
+
+    fn render(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        _: &Candidates<9, 9>,
+        _: &RenderOptions,
+    ) -> String {
+        let mut lines = vec![
+            r"\begin{tabular}{|ccc|ccc|ccc|}".to_string(),
+            r"\hline".to_string(),
+        ];
+
+        for x in 0..9 {
+            let cells: Vec<String> = gameboard
+                .row(x)
+                .iter()
+                .map(|n| match n {
+                    0 => String::new(),
+                    n => n.to_string(),
+                })
+                .collect();
+
+            lines.push(format!(r"{} \\", cells.join(" & ")));
+
+            if x % 3 == 2 {
+                lines.push(r"\hline".to_string());
+            }
+        }
+
+        lines.push(r"\end{tabular}".to_string());
+
+        lines.join("\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str =
+        "....8.....5.63.9...84...27..3.1...5.5...3...2.6...5.1..19...56...84.27.......6...";
+
+    struct Blank;
+
+    impl Renderer for Blank {
+        fn name(&self) -> &'static str {
+            "plain"
+        }
+
+        fn render(&self, _: &Gameboard<9, 9>, _: &Candidates<9, 9>, _: &RenderOptions) -> String {
+            String::new()
+        }
+    }
+
+    #[test]
+    fn renderers_are_picked_by_name() {
+        let gameboard = io::parse_ss(PUZZLE).unwrap();
+        let candidates = Candidates::default();
+        let options = RenderOptions::default();
+        let mut renderers = Renderers::default();
+
+        assert_eq!(
+            renderers.names(),
+            [
+                "plain",
+                "pretty",
+                "ss",
+                "pencilmarks",
+                "json",
+                "svg",
+                "latex"
+            ]
+        );
+        assert!(renderers.get("html").is_none());
+
+        let render = |renderers: &Renderers, name| {
+            renderers
+                .get(name)
+                .unwrap()
+                .render(&gameboard, &candidates, &options)
+        };
+
+        assert_eq!(render(&renderers, "plain"), gameboard.to_string());
+        assert_eq!(render(&renderers, "ss"), io::to_ss(&gameboard));
+        assert!(render(&renderers, "svg").starts_with("<svg"));
+
+        // registering under a taken name replaces the old renderer
+        renderers.register(Box::new(Blank));
+        assert_eq!(renderers.names().len(), 7);
+        assert_eq!(render(&renderers, "plain"), "");
+    }
+
+    #[test]
+    fn json_carries_the_puzzle_fingerprint() {
+        let gameboard = io::parse_ss(PUZZLE).unwrap();
+        let candidates = Candidates::default();
+
+        let own = format!("{{\"fingerprint\":\"{:016x}\",", gameboard.fingerprint());
+        assert!(Json
+            .render(&gameboard, &candidates, &RenderOptions::default())
+            .starts_with(&own));
+
+        let options = RenderOptions {
+            fingerprint: Some(0xab),
+            ..RenderOptions::default()
+        };
+        assert!(Json
+            .render(&gameboard, &candidates, &options)
+            .starts_with("{\"fingerprint\":\"00000000000000ab\",\"board\":[[0,0,0,0,8,"));
+    }
+
+    #[test]
+    fn highlights_mark_recent_and_conflicting_cells() {
+        let mut state = [[0; 9]; 9];
+        state[0][0] = 5;
+        state[0][8] = 5;
+        state[4][4] = 3;
+        let gameboard = Gameboard::from(state);
+        let candidates = Candidates::default();
+
+        let mut options = RenderOptions {
+            recent: vec![(4, 4), (0, 8)],
+            conflicts: true,
+            escapes: true,
+            ..RenderOptions::default()
+        };
+        let plain = Plain.render(&gameboard, &candidates, &options);
+        let lines: Vec<&str> = plain.lines().collect();
+
+        assert!(lines[0].starts_with("\x1b[31m5\x1b[0m . "));
+        assert!(lines[0].ends_with(" \x1b[1;31m5\x1b[0m "));
+        assert_eq!(lines[4], ". . . . \x1b[1m3\x1b[0m . . . . ");
+
+        // without escapes the marks are dropped
+        options.escapes = false;
+        assert_eq!(
+            Plain.render(&gameboard, &candidates, &options),
+            gameboard.to_string()
+        );
+    }
+}
@@ -0,0 +1,521 @@
+use std::{
+    fmt::{Display, Error, Formatter},
+    rc::Rc,
+};
+
+use crate::{
+    candidates::{digits, ToCellMask},
+    patterns::pattern_rules,
+    Candidates, Gameboard,
+};
+
+pub trait Rule<const X: usize, const Y: usize> {
+    fn name(&self) -> &'static str;
+    // one sentence on why the rule's eliminations hold, for teaching
+    fn explanation(&self) -> &'static str;
+    // relative cost of a visit, used to order rules by the non-round-robin strategies
+    fn cost(&self) -> u32;
+    // cells the rule reads, or None when it depends on the whole board
+    fn scope(&self) -> Option<&[(usize, usize)]>;
+    // whether the board keeps to the rule, without solving anything. empty cells never
+    // break it, so a partial board only fails once its filled cells do
+    fn is_satisfied(&self, gameboard: &Gameboard<X, Y>) -> bool;
+    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>);
+}
+
+pub struct ExcludeWhenSolved;
+
+impl<const X: usize, const Y: usize> Rule<X, Y> for ExcludeWhenSolved {
+    fn name(&self) -> &'static str {
+        "exclude_when_solved"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "a solved cell has no candidates left"
+    }
+
+    fn cost(&self) -> u32 {
+        0
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        None
+    }
+
+    fn is_satisfied(&self, _: &Gameboard<X, Y>) -> bool {
+        true
+    }
+
+    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
+        for (x, y) in &gameboard.newly_solved {
+            candidates.mark_as_solved(*x, *y);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Region {
+    pub(crate) positions: Rc<Vec<(usize, usize)>>,
+}
+
+impl Region {
+    #[must_use]
+    pub fn new(positions: Vec<(usize, usize)>) -> Self {
+        Self {
+            positions: Rc::new(positions),
+        }
+    }
+
+    #[must_use]
+    pub fn positions(&self) -> &[(usize, usize)] {
+        &self.positions
+    }
+}
+
+pub struct UniqueByRegion(pub Rc<Region>);
+
+impl<const X: usize, const Y: usize> Rule<X, Y> for UniqueByRegion {
+    fn name(&self) -> &'static str {
+        "unique_by_region"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "a digit placed in a row, column or box is removed from the other cells of it"
+    }
+
+    fn cost(&self) -> u32 {
+        1
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        Some(&self.0.positions)
+    }
+
+    fn is_satisfied(&self, gameboard: &Gameboard<X, Y>) -> bool {
+        let mut seen = 0;
+
+        for (x, y) in self.0.positions.iter() {
+            let n = gameboard.cell(*x, *y);
+
+            if n == 0 {
+                continue;
+            }
+
+            if seen & n.to_cell_mask() > 0 {
+                return false;
+            }
+
+            seen |= n.to_cell_mask();
+        }
+
+        true
+    }
+
+    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
+        for (x, y) in self.0.positions.iter() {
+            if gameboard.cell(*x, *y) == 0 {
+                continue;
+            }
+
+            for (x2, y2) in self.0.positions.iter() {
+                if (x2, y2) == (x, y) {
+                    continue;
+                }
+
+                candidates.exclude_candidate(*x2, *y2, gameboard.cell(*x, *y));
+            }
+        }
+    }
+}
+
+pub struct FillRegionUniquely(pub Rc<Region>);
+
+impl<const X: usize, const Y: usize> Rule<X, Y> for FillRegionUniquely {
+    fn name(&self) -> &'static str {
+        "fill_region_uniquely"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "a digit with only one possible cell left in a row, column or box goes there"
+    }
+
+    fn cost(&self) -> u32 {
+        2
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        Some(&self.0.positions)
+    }
+
+    // every digit from 1 to the region's size appears once. until the region is full
+    // there's room for the rest, and unique_by_region catches repeats
+    fn is_satisfied(&self, gameboard: &Gameboard<X, Y>) -> bool {
+        let positions = &self.0.positions;
+
+        if positions.iter().any(|(x, y)| gameboard.cell(*x, *y) == 0) {
+            return true;
+        }
+
+        let mask = positions
+            .iter()
+            .map(|(x, y)| gameboard.cell(*x, *y))
+            .filter(|n| (1..=positions.len()).contains(&usize::from(*n)))
+            .fold(0, |mask, n| mask | n.to_cell_mask());
+
+        mask.count_ones() as usize == positions.len()
+    }
+
+    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
+        'next_n: for n in digits::<Y>() {
+            // a placed digit isn't excluded from its peers until unique_by_region
+            // has seen it, so don't count on that having happened yet
+            if self
+                .0
+                .positions
+                .iter()
+                .any(|(x, y)| gameboard.cell(*x, *y) == n)
+            {
+                continue;
+            }
+
+            let mut solo_position = None;
+
+            for (x, y) in self.0.positions.iter() {
+                if candidates.cells[*x][*y] & n.to_cell_mask() > 0 {
+                    if solo_position.is_some() {
+                        continue 'next_n;
+                    }
+
+                    solo_position = Some((x, y));
+                }
+            }
+
+            if let Some((x, y)) = solo_position {
+                for (x2, y2) in self.0.positions.iter() {
+                    if (x2, y2) == (x, y) {
+                        candidates.set_exclusive_candidate(*x2, *y2, n);
+                    } else {
+                        candidates.exclude_candidate(*x2, *y2, n);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// a full board with digits from 1 to Y that every rule accepts, regions and variants
+// alike, so it holds for any rule set rather than only the classic one
+#[must_use]
+pub fn is_valid_solution<const X: usize, const Y: usize>(
+    gameboard: &Gameboard<X, Y>,
+    rules: &[Box<dyn Rule<X, Y>>],
+) -> bool {
+    let filled = (0..X).all(|x| {
+        gameboard
+            .row(x)
+            .iter()
+            .all(|n| (1..=Y).contains(&usize::from(*n)))
+    });
+
+    filled && is_consistent(gameboard, rules)
+}
+
+// no filled cell breaks a rule yet, so the board might still be completed. a board
+// that fails this can't be rescued by filling in more cells
+#[must_use]
+pub fn is_consistent<const X: usize, const Y: usize>(
+    gameboard: &Gameboard<X, Y>,
+    rules: &[Box<dyn Rule<X, Y>>],
+) -> bool {
+    rules.iter().all(|rule| rule.is_satisfied(gameboard))
+}
+
+// the region rules and the patterns built on them
+#[must_use]
+pub fn build_9x9_rules() -> Vec<Box<dyn Rule<9, 9>>> {
+    with_patterns(&build_9x9_regions())
+}
+
+/// The classic rules with `boxes` in place of the 3x3 boxes, e.g. a jigsaw's.
+///
+/// # Errors
+///
+/// Fails if the boxes don't tile the board, see [`validate_boxes`].
+pub fn build_9x9_rules_with_boxes(
+    boxes: &[Vec<(usize, usize)>],
+) -> Result<Vec<Box<dyn Rule<9, 9>>>, RegionError> {
+    validate_boxes::<9, 9>(boxes)?;
+
+    let mut regions = build_9x9_regions();
+    regions.truncate(18);
+    regions.extend_from_slice(boxes);
+
+    Ok(with_patterns(&regions))
+}
+
+fn with_patterns(regions: &[Vec<(usize, usize)>]) -> Vec<Box<dyn Rule<9, 9>>> {
+    let mut rules = rules_for_regions(regions);
+    rules.extend(pattern_rules(regions));
+    rules
+}
+
+// the region rules on a board of any size, without the 9x9 patterns. `build_9x9_rules`
+// is this plus the patterns
+#[must_use]
+pub fn build_rules<const N: usize, const R: usize, const C: usize>() -> Vec<Box<dyn Rule<N, N>>> {
+    rules_for_regions(&build_regions::<N, R, C>())
+}
+
+pub(crate) fn rules_for_regions<const X: usize, const Y: usize>(
+    regions: &[Vec<(usize, usize)>],
+) -> Vec<Box<dyn Rule<X, Y>>> {
+    let mut rules: Vec<Box<dyn Rule<X, Y>>> = vec![];
+
+    let regions: Vec<Rc<Region>> = regions
+        .iter()
+        .cloned()
+        .map(Region::new)
+        .map(Rc::new)
+        .collect();
+
+    rules.push(Box::new(ExcludeWhenSolved {}));
+
+    for region in &regions {
+        rules.push(Box::new(UniqueByRegion(region.clone())));
+    }
+
+    for region in &regions {
+        rules.push(Box::new(FillRegionUniquely(region.clone())));
+    }
+
+    rules
+}
+
+// why a set of boxes can't stand in for the 3x3 ones. regions are indices into the set
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RegionError {
+    OutOfBounds {
+        region: usize,
+        position: (usize, usize),
+    },
+    // a box needs one cell per digit
+    WrongSize {
+        region: usize,
+        size: usize,
+    },
+    // in two boxes, or listed twice in one
+    Overlap {
+        position: (usize, usize),
+        regions: (usize, usize),
+    },
+    Uncovered((usize, usize)),
+}
+
+impl Display for RegionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Self::OutOfBounds {
+                region,
+                position: (x, y),
+            } => write!(f, "region {region} has r{}c{} off the board", x + 1, y + 1),
+            Self::WrongSize { region, size } => write!(f, "region {region} has {size} cells"),
+            Self::Overlap {
+                position: (x, y),
+                regions: (first, second),
+            } => write!(
+                f,
+                "r{}c{} is in both region {first} and region {second}",
+                x + 1,
+                y + 1
+            ),
+            Self::Uncovered((x, y)) => write!(f, "r{}c{} isn't in any region", x + 1, y + 1),
+        }
+    }
+}
+
+impl std::error::Error for RegionError {}
+
+/// Checks that `boxes` split an X by Y board into regions of Y cells each, with every
+/// cell in exactly one of them. The rules trust their regions, so a malformed set
+/// would otherwise make unsound eliminations rather than fail.
+///
+/// # Errors
+///
+/// Returns the first problem found, box by box.
+pub fn validate_boxes<const X: usize, const Y: usize>(
+    boxes: &[Vec<(usize, usize)>],
+) -> Result<(), RegionError> {
+    let mut owners = [[None; Y]; X];
+
+    for (region, positions) in boxes.iter().enumerate() {
+        if positions.len() != Y {
+            return Err(RegionError::WrongSize {
+                region,
+                size: positions.len(),
+            });
+        }
+
+        for &(x, y) in positions {
+            if x >= X || y >= Y {
+                return Err(RegionError::OutOfBounds {
+                    region,
+                    position: (x, y),
+                });
+            }
+
+            if let Some(first) = owners[x][y] {
+                return Err(RegionError::Overlap {
+                    position: (x, y),
+                    regions: (first, region),
+                });
+            }
+
+            owners[x][y] = Some(region);
+        }
+    }
+
+    match (0..X)
+        .flat_map(|x| (0..Y).map(move |y| (x, y)))
+        .find(|(x, y)| owners[*x][*y].is_none())
+    {
+        Some(position) => Err(RegionError::Uncovered(position)),
+        None => Ok(()),
+    }
+}
+
+#[must_use]
+pub fn build_9x9_regions() -> Vec<Vec<(usize, usize)>> {
+    build_regions::<9, 3, 3>()
+}
+
+// rows, then columns, then `R` by `C` boxes left to right and top to bottom, for a
+// board of `N` digits, like 5x5 boxes for 25x25 or 2x3 boxes for 6x6
+#[must_use]
+pub fn build_regions<const N: usize, const R: usize, const C: usize>() -> Vec<Vec<(usize, usize)>> {
+    const { assert!(R * C == N, "boxes need one cell per digit") };
+
+    let mut regions: Vec<Vec<(usize, usize)>> = vec![];
+
+    // rows
+    for x in 0..N {
+        regions.push((0..N).map(|y| (x, y)).collect());
+    }
+
+    // columns
+    for y in 0..N {
+        regions.push((0..N).map(|x| (x, y)).collect());
+    }
+
+    // boxes
+    for x_outer in 0..N / R {
+        for y_outer in 0..N / C {
+            regions.push(
+                (0..R)
+                    .flat_map(|x_inner| {
+                        (0..C).map(move |y_inner| (x_outer * R + x_inner, y_outer * C + y_inner))
+                    })
+                    .collect(),
+            );
+        }
+    }
+
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Engine, SolverConfig};
+
+    #[test]
+    fn finds_hidden_singles_past_9_on_25x25() {
+        let regions = build_regions::<25, 5, 5>();
+        let row = FillRegionUniquely(Rc::new(Region::new(regions[0].clone())));
+
+        let gameboard = Gameboard::from([[0; 25]; 25]);
+        let mut candidates = Candidates::<25, 25>::default();
+
+        for y in (0..25).filter(|y| *y != 7) {
+            candidates.exclude_candidate(0, y, 20);
+        }
+
+        row.visit(&gameboard, &mut candidates);
+
+        assert_eq!(
+            candidates.candidates_at((0, 7)).iter().collect::<Vec<_>>(),
+            [20]
+        );
+    }
+
+    #[test]
+    fn boxes_tile_the_board() {
+        assert_eq!(
+            validate_boxes::<25, 25>(&build_regions::<25, 5, 5>()[50..]),
+            Ok(())
+        );
+        assert_eq!(
+            validate_boxes::<6, 6>(&build_regions::<6, 2, 3>()[12..]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn reports_boxes_that_dont_tile_the_board() {
+        let boxes = build_regions::<4, 2, 2>()[8..].to_vec();
+
+        let mut short = boxes.clone();
+        short[0].pop();
+        assert_eq!(
+            validate_boxes::<4, 4>(&short),
+            Err(RegionError::WrongSize { region: 0, size: 3 })
+        );
+
+        let mut outside = boxes.clone();
+        outside[1][0] = (4, 0);
+        assert_eq!(
+            validate_boxes::<4, 4>(&outside),
+            Err(RegionError::OutOfBounds {
+                region: 1,
+                position: (4, 0)
+            })
+        );
+
+        let mut overlapping = boxes.clone();
+        overlapping[1][0] = (0, 0);
+        assert_eq!(
+            validate_boxes::<4, 4>(&overlapping),
+            Err(RegionError::Overlap {
+                position: (0, 0),
+                regions: (0, 1)
+            })
+        );
+
+        assert_eq!(
+            validate_boxes::<4, 4>(&boxes[..3]),
+            Err(RegionError::Uncovered((2, 2)))
+        );
+    }
+
+    #[test]
+    fn solves_a_6x6_board() {
+        let mut gameboard = Gameboard::from([
+            [0, 0, 3, 0, 1, 0],
+            [5, 6, 0, 3, 2, 0],
+            [0, 5, 4, 2, 0, 3],
+            [2, 0, 6, 4, 5, 0],
+            [0, 1, 2, 0, 4, 5],
+            [0, 4, 0, 1, 0, 0],
+        ]);
+        let mut candidates = Candidates::default();
+
+        let rules = build_rules::<6, 2, 3>();
+        Engine::new(build_rules::<6, 2, 3>(), SolverConfig::default()).run(
+            &mut gameboard,
+            &mut candidates,
+            None,
+        );
+
+        assert!(is_valid_solution(&gameboard, &rules));
+    }
+}
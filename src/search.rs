@@ -0,0 +1,235 @@
+use std::time::Instant;
+
+use crate::{
+    build_9x9_regions, is_consistent, is_valid_solution, Backend, Candidates, Engine, Gameboard,
+    Observer, Stats,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CellOrder {
+    // branch on the cell with the fewest candidates left
+    #[default]
+    MinimumRemainingValues,
+    // branch on the first empty cell in reading order
+    FirstUnset,
+}
+
+impl CellOrder {
+    pub const ALL: [Self; 2] = [Self::MinimumRemainingValues, Self::FirstUnset];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::MinimumRemainingValues => "mrv",
+            Self::FirstUnset => "first-unset",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|order| order.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValueOrder {
+    // try digits from 1 up
+    #[default]
+    Ascending,
+    // try first the digits that rule out the fewest candidates among the cell's peers
+    LeastConstraining,
+}
+
+impl ValueOrder {
+    pub const ALL: [Self; 2] = [Self::Ascending, Self::LeastConstraining];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ascending => "ascending",
+            Self::LeastConstraining => "lcv",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|order| order.name() == name)
+    }
+}
+
+impl Engine<9, 9> {
+    // runs the rules, then guesses and backtracks through the journal until the board
+    // is solved or every branch has failed. only the initial logic pass is observed,
+    // unless the backend is hybrid, which also shows each guess, the logic after it and
+    // each backtrack as steps
+    pub fn search(
+        &self,
+        gameboard: &mut Gameboard<9, 9>,
+        candidates: &mut Candidates<9, 9>,
+        mut observer: Option<&mut dyn Observer<9, 9>>,
+    ) -> Stats {
+        let started = Instant::now();
+
+        let mut stats = self.run(gameboard, candidates, reborrow(&mut observer));
+        let regions = self.regions.get_or_init(build_9x9_regions);
+
+        if self.config.backend != Backend::Hybrid {
+            observer = None;
+        }
+
+        // guessing would skip past the position the caller wanted to see
+        if stats.stopped.is_none() && !stats.cancelled {
+            self.branch(gameboard, candidates, regions, &mut observer, &mut stats);
+        }
+
+        stats.elapsed = started.elapsed();
+
+        stats
+    }
+
+    fn branch(
+        &self,
+        gameboard: &mut Gameboard<9, 9>,
+        candidates: &mut Candidates<9, 9>,
+        regions: &[Vec<(usize, usize)>],
+        observer: &mut Option<&mut dyn Observer<9, 9>>,
+        stats: &mut Stats,
+    ) -> bool {
+        // the candidates only catch empty cells with nowhere left to go, so repeated
+        // givens would otherwise be guessed around until the board is full
+        if candidates.is_contradicted(gameboard) || !is_consistent(gameboard, self.rules()) {
+            return false;
+        }
+
+        let Some(position) = self.choose_cell(gameboard, candidates) else {
+            return is_valid_solution(gameboard, self.rules());
+        };
+
+        for n in self.order_values(gameboard, candidates, regions, position) {
+            if self.is_cancelled() {
+                stats.cancelled = true;
+                return false;
+            }
+
+            stats.nodes += 1;
+
+            let mark = candidates.mark();
+            let before = candidates.cells;
+
+            candidates.place(gameboard, position, n);
+
+            if let Some(observer) = observer.as_deref_mut() {
+                observer.observe("guess", &before, gameboard, candidates);
+            }
+
+            let logic = self.run(gameboard, candidates, reborrow(observer));
+            stats.visits += logic.visits;
+            stats.productive_visits += logic.productive_visits;
+            stats.placements += logic.placements + 1;
+
+            if self.branch(gameboard, candidates, regions, observer, stats) {
+                stats.guesses += 1;
+                return true;
+            }
+
+            // left in place, so a cancelled solve hands back the deepest board it reached
+            if stats.cancelled {
+                return false;
+            }
+
+            let before = candidates.cells;
+
+            candidates.rollback_to(gameboard, mark);
+
+            if let Some(observer) = observer.as_deref_mut() {
+                observer.observe("backtrack", &before, gameboard, candidates);
+            }
+        }
+
+        false
+    }
+
+    fn choose_cell(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        candidates: &Candidates<9, 9>,
+    ) -> Option<(usize, usize)> {
+        let mut empty_cells = (0..9)
+            .flat_map(|x| (0..9).map(move |y| (x, y)))
+            .filter(|(x, y)| gameboard.cell(*x, *y) == 0);
+
+        match self.config.cell_order {
+            CellOrder::MinimumRemainingValues => {
+                empty_cells.min_by_key(|(x, y)| candidates.remaining_candidates(*x, *y))
+            }
+            CellOrder::FirstUnset => empty_cells.next(),
+        }
+    }
+
+    fn order_values(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        candidates: &Candidates<9, 9>,
+        regions: &[Vec<(usize, usize)>],
+        position: (usize, usize),
+    ) -> Vec<u8> {
+        let mut digits: Vec<u8> = candidates.candidates_at(position).iter().collect();
+
+        if self.config.value_order == ValueOrder::LeastConstraining {
+            let mut peers: Vec<(usize, usize)> = regions
+                .iter()
+                .filter(|region| region.contains(&position))
+                .flatten()
+                .filter(|(x, y)| (*x, *y) != position && gameboard.cell(*x, *y) == 0)
+                .copied()
+                .collect();
+            peers.sort_unstable();
+            peers.dedup();
+
+            // stable, so ties keep ascending order
+            digits.sort_by_key(|n| {
+                peers
+                    .iter()
+                    .filter(|peer| candidates.candidates_at(**peer).contains(*n))
+                    .count()
+            });
+        }
+
+        digits
+    }
+}
+
+// `as_deref_mut` would keep the observer borrowed for as long as the trait object
+// lives, this lets it go after each logic pass
+fn reborrow<'a>(
+    observer: &'a mut Option<&mut dyn Observer<9, 9>>,
+) -> Option<&'a mut dyn Observer<9, 9>> {
+    match observer {
+        Some(observer) => Some(&mut **observer),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{io, Backend, Solver, SolverConfig};
+
+    #[test]
+    fn repeated_givens_are_rejected_without_guessing() {
+        let mut gameboard = io::parse_ss(&format!("11{}", ".".repeat(79))).unwrap();
+        let solver = Solver::new(
+            SolverConfig {
+                backend: Backend::Search,
+                ..SolverConfig::default()
+            },
+            &[],
+        );
+
+        let (_, stats) = solver.solve(&mut gameboard, None);
+
+        assert!(!gameboard.is_solved());
+        assert_eq!(stats.nodes, 0);
+    }
+}
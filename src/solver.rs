@@ -0,0 +1,702 @@
+use std::fmt::{Display, Error, Formatter};
+
+use crate::{
+    analysis::{is_bookkeeping, settle},
+    build_9x9_rules, build_9x9_rules_with,
+    engine::APPLY_UNIQUES_EXPLANATION,
+    io::{self, ParseError},
+    is_consistent, Candidates, Engine, Gameboard, Observer, Placement, Rule, SolverConfig, Stats,
+    Strategy, Trace, Variant,
+};
+
+// the hybrid backend records its guesses as a step of their own
+const GUESS_EXPLANATION: &str =
+    "no technique makes progress, so a candidate is tried and taken back if it leads nowhere";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Backend {
+    // rules only, which may leave the board unfinished
+    #[default]
+    Logic,
+    // rules, then backtracking over guesses when they stall
+    Search,
+    // search that shows its guesses and backtracks to the observer as steps
+    Hybrid,
+}
+
+impl Backend {
+    pub const ALL: [Self; 3] = [Self::Logic, Self::Search, Self::Hybrid];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Logic => "logic",
+            Self::Search => "search",
+            Self::Hybrid => "hybrid",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|backend| backend.name() == name)
+    }
+}
+
+// what this build can do, for front-ends building settings dynamically
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    pub techniques: Vec<&'static str>,
+    pub variants: Vec<&'static str>,
+    pub strategies: Vec<&'static str>,
+    pub backends: Vec<&'static str>,
+}
+
+// the next move to suggest to someone solving by hand
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Hint {
+    Place {
+        position: (usize, usize),
+        digit: u8,
+        kind: Placement,
+    },
+    // the candidates `technique` rules out, and the cells it reasons from
+    Eliminate {
+        technique: &'static str,
+        cells: Vec<(usize, usize)>,
+        eliminations: Vec<((usize, usize), u8)>,
+    },
+}
+
+// a technique by the name the trace and `SolverConfig::stop_at` use, with what it does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Technique {
+    pub name: &'static str,
+    pub explanation: &'static str,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct SolveResult {
+    // as far as the solve got, so only finished when `solved` is set
+    pub gameboard: Gameboard<9, 9>,
+    pub solved: bool,
+    // techniques that made progress, in the order they were first needed. the search
+    // backend's guesses aren't among them, only counted in `stats`
+    pub techniques: Vec<Technique>,
+    pub stats: Stats,
+}
+
+pub struct Solver {
+    engine: Engine<9, 9>,
+}
+
+impl Solver {
+    #[must_use]
+    pub fn new(config: SolverConfig, variants: &[Variant]) -> Self {
+        Self {
+            engine: Engine::new(build_9x9_rules_with(variants), config),
+        }
+    }
+
+    pub fn solve(
+        &self,
+        gameboard: &mut Gameboard<9, 9>,
+        observer: Option<&mut dyn Observer<9, 9>>,
+    ) -> (Candidates<9, 9>, Stats) {
+        let mut candidates = Candidates::default();
+
+        let stats = self.resume(gameboard, &mut candidates, observer);
+
+        (candidates, stats)
+    }
+
+    // solves a copy of `gameboard`, keeping track of the techniques it took
+    #[must_use]
+    pub fn run(&self, gameboard: &Gameboard<9, 9>) -> SolveResult {
+        let mut gameboard = gameboard.clone();
+        let mut trace = Trace::default();
+
+        let (_, stats) = self.solve(&mut gameboard, Some(&mut trace));
+
+        let techniques = trace
+            .techniques()
+            .into_iter()
+            .filter_map(|name| self.technique(name))
+            .collect();
+
+        SolveResult {
+            solved: gameboard.is_solved(),
+            gameboard,
+            techniques,
+            stats,
+        }
+    }
+
+    // carries on from candidates left by an earlier solve, e.g. from `io::import_state`
+    pub fn resume(
+        &self,
+        gameboard: &mut Gameboard<9, 9>,
+        candidates: &mut Candidates<9, 9>,
+        observer: Option<&mut dyn Observer<9, 9>>,
+    ) -> Stats {
+        match self.engine.config.backend {
+            Backend::Logic => self.engine.run(gameboard, candidates, observer),
+            Backend::Search | Backend::Hybrid => {
+                self.engine.search(gameboard, candidates, observer)
+            }
+        }
+    }
+
+    // a solver that keeps its rules and candidates between puzzles, for batch runs
+    #[must_use]
+    pub fn reusable(config: SolverConfig, variants: &[Variant]) -> ReusableSolver {
+        ReusableSolver {
+            solver: Self::new(config, variants),
+            candidates: Candidates::default(),
+        }
+    }
+
+    // edits one clue in `givens` and brings a board this solver already worked on up to
+    // date, for editors that toggle clues often. each clue set here is journaled with
+    // the deductions it led to, so clearing or changing one rolls back to just before
+    // it and replays the clues set after it. clues that were already in the givens
+    // have no journal entry, so editing one of those restarts from the givens, as does
+    // any edit that leaves the givens contradicting each other. either way the board
+    // ends up as a fresh solve of `givens` would. the clue is set even if it's locked,
+    // since editing clues is the point. the stats are those of the last propagation
+    pub fn update_clue(
+        &self,
+        givens: &mut Gameboard<9, 9>,
+        gameboard: &mut Gameboard<9, 9>,
+        candidates: &mut Candidates<9, 9>,
+        position: (usize, usize),
+        value: u8,
+    ) -> Stats {
+        let previous = givens.cell(position.0, position.1);
+        givens.force_cell(position.0, position.1, value);
+
+        let clues: Vec<_> = candidates.clue_marks().collect();
+        let edited = clues.iter().position(|&(_, cell)| cell == position);
+
+        let replay = match edited {
+            Some(index) => {
+                candidates.rollback_to(gameboard, clues[index].0);
+                clues[index..].iter().map(|&(_, cell)| cell).collect()
+            }
+            None if previous == 0 => vec![position],
+            None => {
+                // the base givens are the ones with no clue entry
+                let mut base = givens.state;
+                for &(_, (x, y)) in &clues {
+                    base[x][y] = 0;
+                }
+                base[position.0][position.1] = 0;
+
+                // the givens' own list of solved cells can name clues that are gone now
+                *gameboard = Gameboard {
+                    locked: gameboard.locked,
+                    ..Gameboard::from(base)
+                };
+                *candidates = Candidates::default();
+
+                let mut replay: Vec<_> = clues.iter().map(|&(_, cell)| cell).collect();
+                replay.insert(0, position);
+                replay
+            }
+        };
+
+        let mut stats = self.resume(gameboard, candidates, None);
+
+        for (x, y) in replay {
+            let value = givens.cell(x, y);
+
+            if value != 0 {
+                candidates.place_clue(gameboard, (x, y), value);
+                stats = self.resume(gameboard, candidates, None);
+            }
+        }
+
+        // where a contradiction leaves the board depends on the order clues came in, so
+        // those boards are solved again from the givens to match a fresh solve
+        if candidates.is_contradicted(gameboard) || !is_consistent(gameboard, self.engine.rules()) {
+            *gameboard = Gameboard {
+                locked: gameboard.locked,
+                ..Gameboard::from(givens.state)
+            };
+            *candidates = Candidates::default();
+            stats = self.resume(gameboard, candidates, None);
+        }
+
+        stats
+    }
+
+    pub(crate) fn rules(&self) -> &[Box<dyn Rule<9, 9>>] {
+        self.engine.rules()
+    }
+
+    // a placement if any rule leads to one. otherwise, with `eliminations`, whatever
+    // rules out the most candidates, the cheapest rule on a tie, so an app can nudge
+    // without giving a cell away
+    #[must_use]
+    pub fn hint(
+        &self,
+        gameboard: &Gameboard<9, 9>,
+        candidates: &Candidates<9, 9>,
+        eliminations: bool,
+    ) -> Option<Hint> {
+        let rules = self.engine.rules();
+
+        let mut settled = candidates.clone();
+        settle(rules, gameboard, &mut settled);
+
+        let placement = |candidates: &Candidates<9, 9>| {
+            let position = *candidates.pending_singles(gameboard).first()?;
+
+            Some(Hint::Place {
+                position,
+                digit: candidates.candidates_at(position).iter().next()?,
+                kind: if candidates.narrowed_by(position) == "fill_region_uniquely" {
+                    Placement::HiddenSingle
+                } else {
+                    Placement::NakedSingle
+                },
+            })
+        };
+
+        if let Some(hint) = placement(&settled) {
+            return Some(hint);
+        }
+
+        let mut trials: Vec<(&dyn Rule<9, 9>, Candidates<9, 9>)> = rules
+            .iter()
+            .filter(|rule| !is_bookkeeping(rule.as_ref()))
+            .map(|rule| {
+                let mut trial = settled.clone();
+                trial.set_cause(rule.name());
+                rule.visit(gameboard, &mut trial);
+                (rule.as_ref(), trial)
+            })
+            .filter(|(_, trial)| trial.cells != settled.cells)
+            .collect();
+
+        trials.sort_by_key(|(rule, _)| rule.cost());
+
+        if let Some(hint) = trials.iter().find_map(|(_, trial)| placement(trial)) {
+            return Some(hint);
+        }
+
+        if !eliminations {
+            return None;
+        }
+
+        let removed = |trial: &Candidates<9, 9>| -> Vec<((usize, usize), u8)> {
+            (0..9)
+                .flat_map(|x| (0..9).map(move |y| (x, y)))
+                .flat_map(|position| {
+                    let kept = trial.candidates_at(position);
+
+                    settled
+                        .candidates_at(position)
+                        .iter()
+                        .filter(move |n| !kept.contains(*n))
+                        .map(move |n| (position, n))
+                })
+                .collect()
+        };
+
+        // max_by_key keeps the last of equal counts, so reversed it keeps the cheapest
+        let (rule, trial) = trials
+            .iter()
+            .rev()
+            .max_by_key(|(_, trial)| removed(trial).len())?;
+
+        let eliminations = removed(trial);
+
+        let cells = if let Some(scope) = rule.scope() {
+            scope.to_vec()
+        } else {
+            let mut cells: Vec<(usize, usize)> =
+                eliminations.iter().map(|(position, _)| *position).collect();
+            cells.dedup();
+            cells
+        };
+
+        Some(Hint::Eliminate {
+            technique: rule.name(),
+            cells,
+            eliminations,
+        })
+    }
+
+    // names of the rules this solver runs, plus placing singles, for `stop_at`
+    #[must_use]
+    pub fn techniques(&self) -> Vec<&'static str> {
+        Self::technique_names(self.engine.rules())
+    }
+
+    // one of `techniques`, or the hybrid backend's guessing
+    #[must_use]
+    pub fn technique(&self, name: &str) -> Option<Technique> {
+        match name {
+            "apply_uniques" => Some(Technique {
+                name: "apply_uniques",
+                explanation: APPLY_UNIQUES_EXPLANATION,
+            }),
+            "guess" => Some(Technique {
+                name: "guess",
+                explanation: GUESS_EXPLANATION,
+            }),
+            _ => self
+                .engine
+                .rules()
+                .iter()
+                .find(|rule| rule.name() == name)
+                .map(|rule| Technique {
+                    name: rule.name(),
+                    explanation: rule.explanation(),
+                }),
+        }
+    }
+
+    fn technique_names(rules: &[Box<dyn Rule<9, 9>>]) -> Vec<&'static str> {
+        let mut techniques: Vec<&'static str> = vec![];
+
+        for name in rules
+            .iter()
+            .map(|rule| rule.name())
+            .chain(["apply_uniques"])
+        {
+            if !techniques.contains(&name) {
+                techniques.push(name);
+            }
+        }
+
+        techniques
+    }
+
+    #[must_use]
+    pub fn capabilities() -> Capabilities {
+        Capabilities {
+            techniques: Self::technique_names(&build_9x9_rules()),
+            variants: Variant::NAMES.to_vec(),
+            strategies: Strategy::ALL
+                .iter()
+                .map(|strategy| strategy.name())
+                .collect(),
+            backends: Backend::ALL.iter().map(|backend| backend.name()).collect(),
+        }
+    }
+}
+
+pub struct ReusableSolver {
+    solver: Solver,
+    candidates: Candidates<9, 9>,
+}
+
+impl ReusableSolver {
+    pub fn reset(&mut self) {
+        self.candidates.reset();
+    }
+
+    // resets, then solves `gameboard` with the candidates left for `candidates` to read
+    pub fn solve(
+        &mut self,
+        gameboard: &mut Gameboard<9, 9>,
+        observer: Option<&mut dyn Observer<9, 9>>,
+    ) -> Stats {
+        self.reset();
+        self.solver
+            .resume(gameboard, &mut self.candidates, observer)
+    }
+
+    #[must_use]
+    pub fn candidates(&self) -> &Candidates<9, 9> {
+        &self.candidates
+    }
+
+    #[must_use]
+    pub fn solver(&self) -> &Solver {
+        &self.solver
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SudokuError {
+    Parse(ParseError),
+    // the givens contradict each other, so no guess leads anywhere
+    NoSolution,
+}
+
+impl Display for SudokuError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Self::Parse(error) => write!(f, "{error}"),
+            Self::NoSolution => write!(f, "puzzle has no solution"),
+        }
+    }
+}
+
+impl std::error::Error for SudokuError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse(error) => Some(error),
+            Self::NoSolution => None,
+        }
+    }
+}
+
+impl From<ParseError> for SudokuError {
+    fn from(error: ParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+/// Solves a classic puzzle given as 81 characters, digits for givens and `.` or `0`
+/// for blanks, and returns the solution in the same layout. Falls back to guessing
+/// when the rules stall, so any solvable puzzle comes back finished.
+///
+/// # Errors
+///
+/// Fails if the puzzle doesn't parse or has no solution.
+pub fn solve_str(puzzle: &str) -> Result<String, SudokuError> {
+    let mut gameboard = io::parse_ss(puzzle)?;
+
+    let config = SolverConfig {
+        backend: Backend::Search,
+        ..SolverConfig::default()
+    };
+    let solver = Solver::new(config, &[]);
+
+    // repeated givens can't be solved, so don't leave it to the search to find out
+    if !is_consistent(&gameboard, solver.engine.rules()) {
+        return Err(SudokuError::NoSolution);
+    }
+
+    solver.solve(&mut gameboard, None);
+
+    if !gameboard.is_solved() {
+        return Err(SudokuError::NoSolution);
+    }
+
+    Ok(gameboard
+        .state
+        .iter()
+        .flatten()
+        .map(|n| char::from(b'0' + n))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str =
+        ".....541...18......95....62.7...49......7....3..6.....1.84..7.5.64.5..31....1....";
+
+    fn fresh(solver: &Solver, givens: &Gameboard<9, 9>) -> [[u8; 9]; 9] {
+        let mut gameboard = Gameboard::from(givens.state);
+        solver.solve(&mut gameboard, None);
+        gameboard.state
+    }
+
+    #[test]
+    fn solve_str_reports_repeated_givens() {
+        assert_eq!(
+            solve_str(&format!("11{}", ".".repeat(79))),
+            Err(SudokuError::NoSolution)
+        );
+        assert_eq!(
+            solve_str(PUZZLE).unwrap(),
+            "683295417421867359795341862572134986846579123319682574138426795964758231257913648"
+        );
+    }
+
+    #[test]
+    fn update_clue_matches_a_fresh_solve() {
+        let solver = Solver::new(SolverConfig::default(), &[]);
+        let full = io::parse_sdm(PUZZLE).unwrap().remove(0);
+
+        let search = Solver::new(
+            SolverConfig {
+                backend: Backend::Search,
+                ..SolverConfig::default()
+            },
+            &[],
+        );
+        let solution = search.run(&full).gameboard;
+        assert!(solution.is_solved());
+
+        // take a few clues out, to put back one at a time
+        let clues: Vec<_> = (0..81)
+            .map(|index| (index / 9, index % 9))
+            .filter(|&(x, y)| full.cell(x, y) != 0)
+            .take(4)
+            .collect();
+        let mut givens = Gameboard::from(full.state);
+        for &(x, y) in &clues {
+            givens.force_cell(x, y, 0);
+        }
+
+        let mut gameboard = Gameboard::from(givens.state);
+        let (mut candidates, _) = solver.solve(&mut gameboard, None);
+
+        let [a, b, c, d] = clues[..] else {
+            unreachable!()
+        };
+        let right = |(x, y): (usize, usize)| solution.cell(x, y);
+        // a digit the clue could have been, but isn't
+        let wrong = |(x, y): (usize, usize)| (1..=9).find(|&digit| digit != right((x, y))).unwrap();
+
+        let mut update = |givens: &mut Gameboard<9, 9>, position, value| {
+            solver.update_clue(givens, &mut gameboard, &mut candidates, position, value);
+            assert_eq!(
+                gameboard.state,
+                fresh(&solver, givens),
+                "after setting {position:?} to {value}"
+            );
+        };
+
+        update(&mut givens, a, right(a));
+        update(&mut givens, b, wrong(b));
+        update(&mut givens, c, right(c));
+        update(&mut givens, d, right(d));
+        // changing and clearing clues set earlier, then one that was there from the start
+        update(&mut givens, b, right(b));
+        update(&mut givens, c, 0);
+        update(&mut givens, a, wrong(a));
+        let given = (0..81)
+            .map(|index| (index / 9, index % 9))
+            .find(|&cell| givens.cell(cell.0, cell.1) != 0 && !clues.contains(&cell))
+            .unwrap();
+        update(&mut givens, given, 0);
+        update(&mut givens, a, right(a));
+        update(&mut givens, c, right(c));
+        update(&mut givens, given, right(given));
+
+        assert_eq!(givens.state, full.state);
+        assert_eq!(gameboard.state, fresh(&solver, &full));
+    }
+
+    #[test]
+    fn update_clue_matches_a_fresh_solve_on_deduced_and_conflicting_cells() {
+        let solver = Solver::new(SolverConfig::default(), &[]);
+        // the rules fill some cells in and then stall
+        let start = io::parse_ss(
+            ".....5..389.6.......7......7..4....5......3...1...27684..3....1...92..7..7....8.4",
+        )
+        .unwrap();
+        let mut givens = Gameboard::from(start.state);
+
+        let mut gameboard = Gameboard::from(givens.state);
+        let (mut candidates, _) = solver.solve(&mut gameboard, None);
+
+        let cells = || (0..81).map(|index| (index / 9, index % 9));
+        // a cell the rules filled in, and an empty one with a digit ruled out
+        let deduced = cells()
+            .find(|&(x, y)| givens.cell(x, y) == 0 && gameboard.cell(x, y) != 0)
+            .unwrap();
+        let (empty, excluded) = cells()
+            .filter(|&(x, y)| gameboard.cell(x, y) == 0)
+            .find_map(|position| {
+                let allowed = candidates.candidates_at(position);
+                (1..=9)
+                    .find(|digit| !allowed.contains(*digit))
+                    .map(|digit| (position, digit))
+            })
+            .unwrap();
+        let digit = gameboard.cell(deduced.0, deduced.1);
+
+        let mut update = |givens: &mut Gameboard<9, 9>, position, value| {
+            solver.update_clue(givens, &mut gameboard, &mut candidates, position, value);
+            assert_eq!(
+                gameboard.state,
+                fresh(&solver, givens),
+                "after setting {position:?} to {value}"
+            );
+        };
+
+        // the digit the rules found, then another one, then nothing
+        update(&mut givens, deduced, digit);
+        update(&mut givens, deduced, digit % 9 + 1);
+        update(&mut givens, deduced, 0);
+        // a clue the candidates had already ruled out, and clearing it again
+        update(&mut givens, empty, excluded);
+        update(&mut givens, empty, 0);
+        // clearing a cell that was never a clue
+        update(&mut givens, deduced, 0);
+
+        assert_eq!(givens.state, start.state);
+    }
+
+    #[test]
+    fn hybrid_backend_traces_its_guesses() {
+        // the rules stall on this one after a few placements
+        let stalled = io::parse_ss(
+            ".....5..389.6.......7......7..4....5......3...1...27684..3....1...92..7..7....8.4",
+        )
+        .unwrap();
+
+        let mut logic = stalled.clone();
+        Solver::new(SolverConfig::default(), &[]).solve(&mut logic, None);
+        assert!(!logic.is_solved());
+
+        let mut gameboard = stalled.clone();
+        let mut trace = Trace::default();
+        let (_, stats) = Solver::new(SolverConfig::default().backend(Backend::Hybrid), &[])
+            .solve(&mut gameboard, Some(&mut trace));
+
+        assert!(gameboard.is_solved());
+        assert!(stats.guesses > 0);
+        assert!(trace.techniques().contains(&"guess"));
+    }
+
+    #[test]
+    fn reusable_solver_matches_fresh_solves() {
+        let solver = Solver::new(SolverConfig::default(), &[]);
+        let mut reusable = Solver::reusable(SolverConfig::default(), &[]);
+
+        for puzzle in [
+            PUZZLE,
+            ".....5..389.6.......7......7..4....5......3...1...27684..3....1...92..7..7....8.4",
+            PUZZLE,
+        ] {
+            let givens = io::parse_ss(puzzle).unwrap();
+            let mut gameboard = givens.clone();
+            reusable.solve(&mut gameboard, None);
+
+            assert_eq!(gameboard.state, fresh(&solver, &givens));
+        }
+    }
+
+    #[test]
+    fn empty_rectangle_steps_name_their_box_and_pivot() {
+        let puzzle = io::parse_ss(
+            "..78.26......4..9.....3..4..8.......6..15.2..31.2...7...17.....2.8...9.....4...16",
+        )
+        .unwrap();
+        let solver = Solver::new(SolverConfig::default(), &[]);
+        let mut trace = Trace::default();
+
+        let mut gameboard = puzzle.clone();
+        solver.solve(&mut gameboard, Some(&mut trace));
+
+        let details: Vec<_> = trace
+            .steps
+            .iter()
+            .filter_map(|step| Some((step.action.as_str(), step.detail.as_deref()?)))
+            .collect();
+
+        assert!(details.contains(&("empty_rectangle", "box 4, pivot r4c3")));
+        assert!(details
+            .iter()
+            .all(|(action, _)| *action == "empty_rectangle"));
+        assert!(trace
+            .snapshot()
+            .contains("empty_rectangle 1 (box 4, pivot r4c3)\n"));
+    }
+}
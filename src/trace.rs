@@ -0,0 +1,354 @@
+use std::io::Write;
+
+use crate::{CandidateWord, Candidates, Gameboard, Rule};
+
+pub trait Observer<const X: usize, const Y: usize> {
+    // called after each productive step with the candidates from before it
+    fn observe(
+        &mut self,
+        action: &str,
+        before: &[[CandidateWord; Y]; X],
+        gameboard: &Gameboard<X, Y>,
+        candidates: &Candidates<X, Y>,
+    );
+}
+
+// one JSON object per line: the action taken plus the board and candidates after it.
+// write errors are held until `finish` so recording doesn't interrupt the solve
+pub struct History {
+    writer: Box<dyn Write>,
+    step: usize,
+    error: Option<std::io::Error>,
+}
+
+impl History {
+    #[must_use]
+    pub fn new(writer: Box<dyn Write>) -> Self {
+        Self {
+            writer,
+            step: 0,
+            error: None,
+        }
+    }
+
+    fn write_step<const X: usize, const Y: usize>(
+        &mut self,
+        action: &str,
+        gameboard: &Gameboard<X, Y>,
+        candidates: &Candidates<X, Y>,
+    ) -> std::io::Result<()> {
+        write!(
+            self.writer,
+            "{{\"step\":{},\"action\":\"{action}\",\"board\":[",
+            self.step
+        )?;
+
+        for x in 0..X {
+            if x > 0 {
+                write!(self.writer, ",")?;
+            }
+
+            let row: Vec<String> = (0..Y).map(|y| gameboard.cell(x, y).to_string()).collect();
+            write!(self.writer, "[{}]", row.join(","))?;
+        }
+
+        write!(self.writer, "],\"candidates\":[")?;
+
+        for x in 0..X {
+            if x > 0 {
+                write!(self.writer, ",")?;
+            }
+
+            let row: Vec<String> = (0..Y)
+                .map(|y| {
+                    let digits: Vec<String> = candidates
+                        .candidates_at((x, y))
+                        .iter()
+                        .map(|n| n.to_string())
+                        .collect();
+                    format!("[{}]", digits.join(","))
+                })
+                .collect();
+            write!(self.writer, "[{}]", row.join(","))?;
+        }
+
+        writeln!(self.writer, "]}}")
+    }
+
+    /// Flushes the writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error hit while recording, or any error from flushing.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        match self.error {
+            Some(error) => Err(error),
+            None => self.writer.flush(),
+        }
+    }
+}
+
+impl<const X: usize, const Y: usize> Observer<X, Y> for History {
+    fn observe(
+        &mut self,
+        action: &str,
+        _: &[[CandidateWord; Y]; X],
+        gameboard: &Gameboard<X, Y>,
+        candidates: &Candidates<X, Y>,
+    ) {
+        if self.error.is_some() {
+            return;
+        }
+
+        if let Err(error) = self.write_step(action, gameboard, candidates) {
+            self.error = Some(error);
+        }
+
+        self.step += 1;
+    }
+}
+
+// passes every step on to both observers, e.g. to keep a trace while writing a history
+pub struct Tee<'a, const X: usize, const Y: usize>(
+    pub &'a mut dyn Observer<X, Y>,
+    pub &'a mut dyn Observer<X, Y>,
+);
+
+impl<const X: usize, const Y: usize> Observer<X, Y> for Tee<'_, X, Y> {
+    fn observe(
+        &mut self,
+        action: &str,
+        before: &[[CandidateWord; Y]; X],
+        gameboard: &Gameboard<X, Y>,
+        candidates: &Candidates<X, Y>,
+    ) {
+        self.0.observe(action, before, gameboard, candidates);
+        self.1.observe(action, before, gameboard, candidates);
+    }
+}
+
+// how a cell came to be filled in. the rules leave hidden singles as cells with one
+// candidate too, so this goes by what narrowed the cell last
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Placement {
+    // eliminations left the cell one candidate
+    NakedSingle,
+    // the only cell left for a digit in a row, column or box
+    HiddenSingle,
+    Guess,
+}
+
+impl Placement {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::NakedSingle => "naked",
+            Self::HiddenSingle => "hidden",
+            Self::Guess => "guess",
+        }
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct TraceStep {
+    pub action: String,
+    // what the step found beyond its action, like `box 5, pivot r4c6` for an empty
+    // rectangle
+    pub detail: Option<String>,
+    pub eliminations: u32,
+    // cells the step filled in
+    pub placed: Vec<((usize, usize), Placement)>,
+}
+
+// in-memory record of the steps a solve took
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub steps: Vec<TraceStep>,
+}
+
+impl<const X: usize, const Y: usize> Observer<X, Y> for Trace {
+    fn observe(
+        &mut self,
+        action: &str,
+        before: &[[CandidateWord; Y]; X],
+        gameboard: &Gameboard<X, Y>,
+        candidates: &Candidates<X, Y>,
+    ) {
+        // solved cells lose all their candidates, which isn't a deduction
+        let eliminations = (0..X)
+            .flat_map(|x| (0..Y).map(move |y| (x, y)))
+            .filter(|(x, y)| gameboard.cell(*x, *y) == 0)
+            .map(|(x, y)| (before[x][y] & !candidates.cells[x][y]).count_ones())
+            .sum();
+
+        let placed = match action {
+            "apply_uniques" => gameboard
+                .newly_solved
+                .iter()
+                .map(|position| {
+                    let placement = match candidates.narrowed_by(*position) {
+                        "fill_region_uniquely" => Placement::HiddenSingle,
+                        _ => Placement::NakedSingle,
+                    };
+
+                    (*position, placement)
+                })
+                .collect(),
+            // newly solved cells from before the guess may not have been cleared yet
+            "guess" => gameboard
+                .newly_solved
+                .last()
+                .map(|position| (*position, Placement::Guess))
+                .into_iter()
+                .collect(),
+            _ => vec![],
+        };
+
+        self.steps.push(TraceStep {
+            action: action.to_string(),
+            detail: candidates.detail(action).map(str::to_string),
+            eliminations,
+            placed,
+        });
+    }
+}
+
+impl Trace {
+    // actions that changed something, in the order they were first used
+    #[must_use]
+    pub fn techniques(&self) -> Vec<&str> {
+        let mut techniques = vec![];
+
+        for step in &self.steps {
+            if (step.eliminations > 0 || !step.placed.is_empty())
+                && !techniques.contains(&step.action.as_str())
+            {
+                techniques.push(step.action.as_str());
+            }
+        }
+
+        techniques
+    }
+
+    // cells filled in by the latest step that filled any
+    #[must_use]
+    pub fn last_placements(&self) -> Vec<(usize, usize)> {
+        self.steps
+            .iter()
+            .rev()
+            .find(|step| !step.placed.is_empty())
+            .map_or(vec![], |step| {
+                step.placed.iter().map(|(position, _)| *position).collect()
+            })
+    }
+
+    // one line per step with the action, how many candidates it removed, the cells it
+    // filled in order and any detail, e.g. `apply_uniques 0 r7c5:naked` or
+    // `empty_rectangle 1 (box 5, pivot r4c6)`, for comparing solve paths across versions
+    #[must_use]
+    pub fn snapshot(&self) -> String {
+        let lines: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| {
+                let placed = step.placed.iter().map(|((x, y), placement)| {
+                    format!(" r{}c{}:{}", x + 1, y + 1, placement.name())
+                });
+
+                let detail = step
+                    .detail
+                    .as_ref()
+                    .map_or(String::new(), |detail| format!(" ({detail})"));
+
+                format!("{} {}", step.action, step.eliminations)
+                    + &placed.collect::<String>()
+                    + &detail
+            })
+            .collect();
+
+        lines.join("\n") + "\n"
+    }
+
+    // how many placements were of `kind` over the whole solve
+    #[must_use]
+    pub fn count_placements(&self, kind: Placement) -> usize {
+        self.steps
+            .iter()
+            .flat_map(|step| &step.placed)
+            .filter(|(_, placement)| *placement == kind)
+            .count()
+    }
+
+    // how a human would experience the solve path, 100 being all singles and no slog.
+    // a deduction costs the `Rule::cost` of the rule that made it, with hidden singles
+    // free and anything that isn't one of `rules` costing more than all of them. a long
+    // slog of eliminations between placements costs 1 a step on top
+    #[must_use]
+    pub fn pleasantness<const X: usize, const Y: usize>(
+        &self,
+        rules: &[Box<dyn Rule<X, Y>>],
+    ) -> u32 {
+        let unknown = rules.iter().map(|rule| rule.cost()).max().unwrap_or(0) + 1;
+
+        let mut penalty = 0;
+        let mut since_placement = 0;
+
+        for step in &self.steps {
+            if !step.placed.is_empty() {
+                since_placement = 0;
+                continue;
+            }
+
+            penalty += match step.action.as_str() {
+                // pencil-mark bookkeeping rather than a deduction
+                "exclude_when_solved" | "unique_by_region" => continue,
+                "fill_region_uniquely" => 0,
+                action => rules
+                    .iter()
+                    .find(|rule| rule.name() == action)
+                    .map_or(unknown, |rule| rule.cost()),
+            };
+
+            since_placement += step.eliminations;
+
+            if since_placement > 20 {
+                penalty += 1;
+            }
+        }
+
+        100_u32.saturating_sub(penalty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{io, Solver, SolverConfig};
+
+    fn snapshot_of(puzzle: &str) -> String {
+        let mut gameboard = io::parse_ss(puzzle).unwrap();
+        let mut trace = Trace::default();
+        Solver::new(SolverConfig::default(), &[]).solve(&mut gameboard, Some(&mut trace));
+        trace.snapshot()
+    }
+
+    #[test]
+    fn snapshots_list_each_step_and_placement_once() {
+        let puzzle =
+            "..3.2.6..9..3.5..1..18.64....81.29..7.......8..67.82....26.95..8..2.3..9..5.1.3..";
+        let snapshot = snapshot_of(puzzle);
+
+        assert_eq!(snapshot, snapshot_of(puzzle));
+        assert!(snapshot.starts_with("exclude_when_solved 0\n") && snapshot.ends_with('\n'));
+
+        let placed: Vec<&str> = snapshot
+            .split_whitespace()
+            .filter(|token| token.ends_with(":naked") || token.ends_with(":hidden"))
+            .collect();
+        let empty = puzzle.chars().filter(|c| *c == '.').count();
+        assert_eq!(placed.len(), empty);
+        assert!(placed.contains(&"r1c2:hidden"));
+    }
+}
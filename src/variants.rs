@@ -0,0 +1,1038 @@
+use std::rc::Rc;
+
+use crate::{
+    build_9x9_rules,
+    candidates::{digits, ToCellMask},
+    CandidateSet, CandidateWord, Candidates, FillRegionUniquely, Gameboard, Region, Rule,
+    UniqueByRegion,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Variant {
+    // adjacent cells along the path differ by at least `difference`,
+    // 5 for German whispers and 4 for Dutch
+    Whisper {
+        difference: u8,
+        path: Vec<(usize, usize)>,
+    },
+    // the path holds a set of consecutive digits in any order
+    Renban {
+        path: Vec<(usize, usize)>,
+    },
+    // every listed digit appears in the 2x2 block whose top-left cell is `corner`
+    Quadruple {
+        corner: (usize, usize),
+        digits: Vec<u8>,
+    },
+    // both long diagonals hold every digit once, as in X-sudoku
+    Diagonals,
+    // cells a chess knight's move apart differ
+    AntiKnight,
+    // the cage's digits differ and add up to `sum`
+    Killer {
+        sum: u32,
+        cage: Vec<(usize, usize)>,
+    },
+    // marked orthogonal neighbours add up to 10 for an X or 5 for a V. with
+    // `negative`, no unmarked neighbours do, so a puzzle has one of these holding
+    // all its marks
+    Xv {
+        pairs: Vec<(u8, [(usize, usize); 2])>,
+        negative: bool,
+    },
+    // each cell of `cells` holds the same digit as the cell at the same index in
+    // `twin`, so the twin can be shifted, turned or mirrored
+    Clone {
+        cells: Vec<(usize, usize)>,
+        twin: Vec<(usize, usize)>,
+    },
+}
+
+impl Variant {
+    // keywords naming each variant in the extended input format
+    pub const NAMES: [&'static str; 8] = [
+        "whisper",
+        "renban",
+        "quadruple",
+        "diagonals",
+        "anti-knight",
+        "killer",
+        "xv",
+        "clone",
+    ];
+
+    // its keyword in `NAMES`
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Whisper { .. } => "whisper",
+            Self::Renban { .. } => "renban",
+            Self::Quadruple { .. } => "quadruple",
+            Self::Diagonals => "diagonals",
+            Self::AntiKnight => "anti-knight",
+            Self::Killer { .. } => "killer",
+            Self::Xv { .. } => "xv",
+            Self::Clone { .. } => "clone",
+        }
+    }
+
+    // most variants are one rule, but the diagonals work like two extra regions
+    #[must_use]
+    pub fn to_rules<const X: usize, const Y: usize>(&self) -> Vec<Box<dyn Rule<X, Y>>> {
+        match self {
+            Self::Whisper { difference, path } => {
+                vec![Box::new(WhisperLine::new(path.clone(), *difference))]
+            }
+            Self::Renban { path } => vec![Box::new(RenbanLine::new(path.clone()))],
+            Self::Quadruple { corner, digits } => {
+                vec![Box::new(Quadruple::new(*corner, digits.clone()))]
+            }
+            Self::Diagonals => {
+                let diagonals = [
+                    (0..X).map(|i| (i, i)).collect(),
+                    (0..X).map(|i| (i, X - 1 - i)).collect(),
+                ]
+                .map(|positions| Rc::new(Region::new(positions)));
+
+                let mut rules: Vec<Box<dyn Rule<X, Y>>> = vec![];
+
+                for diagonal in &diagonals {
+                    rules.push(Box::new(UniqueByRegion(diagonal.clone())));
+                    rules.push(Box::new(FillRegionUniquely(diagonal.clone())));
+                }
+
+                rules
+            }
+            Self::AntiKnight => vec![Box::new(AntiKnight)],
+            Self::Killer { sum, cage } => vec![Box::new(KillerCage::new(cage.clone(), *sum))],
+            Self::Xv { pairs, negative } => {
+                let mut rules: Vec<Box<dyn Rule<X, Y>>> = pairs
+                    .iter()
+                    .map(|(sum, cells)| -> Box<dyn Rule<X, Y>> {
+                        Box::new(XvSum::new(*cells, *sum))
+                    })
+                    .collect();
+
+                if *negative {
+                    let marked = pairs.iter().map(|(_, cells)| *cells).collect();
+                    rules.push(Box::new(XvNegative::new(marked)));
+                }
+
+                rules
+            }
+            Self::Clone { cells, twin } => {
+                vec![Box::new(CloneRegion::new(cells.clone(), twin.clone()))]
+            }
+        }
+    }
+}
+
+// the classic rules plus every variant's, so variants combine freely
+#[must_use]
+pub fn build_9x9_rules_with(variants: &[Variant]) -> Vec<Box<dyn Rule<9, 9>>> {
+    let mut rules = build_9x9_rules();
+
+    rules.extend(variants.iter().flat_map(Variant::to_rules));
+
+    rules
+}
+
+pub struct WhisperLine {
+    path: Vec<(usize, usize)>,
+    difference: u8,
+}
+
+impl WhisperLine {
+    #[must_use]
+    pub fn new(path: Vec<(usize, usize)>, difference: u8) -> Self {
+        Self { path, difference }
+    }
+
+    // a digit survives if it is far enough from either end of the neighbour's range
+    fn prune<const X: usize, const Y: usize>(
+        &self,
+        gameboard: &Gameboard<X, Y>,
+        candidates: &mut Candidates<X, Y>,
+        (x, y): (usize, usize),
+        neighbour: (usize, usize),
+    ) {
+        if gameboard.cell(x, y) != 0 {
+            return;
+        }
+
+        let mask = candidates.effective_mask(gameboard, neighbour);
+
+        if mask == 0 {
+            return;
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let (low, high) = (
+            mask.trailing_zeros() as u8 + 1,
+            (CandidateWord::BITS - mask.leading_zeros()) as u8,
+        );
+
+        // saturating, since `new` takes any difference, even ones no digits can have
+        for n in digits::<Y>() {
+            if n.saturating_add(self.difference) > high && n < low.saturating_add(self.difference) {
+                candidates.exclude_candidate(x, y, n);
+            }
+        }
+    }
+}
+
+impl<const X: usize, const Y: usize> Rule<X, Y> for WhisperLine {
+    fn name(&self) -> &'static str {
+        "whisper_line"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "neighbours on a whisper line differ by at least its difference, so digits too close to all of a neighbour's candidates are removed"
+    }
+
+    fn cost(&self) -> u32 {
+        2
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        Some(&self.path)
+    }
+
+    fn is_satisfied(&self, gameboard: &Gameboard<X, Y>) -> bool {
+        self.path.windows(2).all(|pair| {
+            let (a, b) = (
+                gameboard.cell(pair[0].0, pair[0].1),
+                gameboard.cell(pair[1].0, pair[1].1),
+            );
+            a == 0 || b == 0 || a.abs_diff(b) >= self.difference
+        })
+    }
+
+    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
+        for pair in self.path.windows(2) {
+            self.prune(gameboard, candidates, pair[0], pair[1]);
+            self.prune(gameboard, candidates, pair[1], pair[0]);
+        }
+    }
+}
+
+pub struct RenbanLine {
+    path: Vec<(usize, usize)>,
+    // the lowest run of digits as long as the path, or none for a path too long for
+    // a mask's digits, which no board can fill with different ones
+    run: CandidateWord,
+}
+
+impl RenbanLine {
+    #[must_use]
+    pub fn new(path: Vec<(usize, usize)>) -> Self {
+        let run = u32::try_from(path.len())
+            .ok()
+            .filter(|length| (1..=CandidateWord::BITS).contains(length))
+            .map_or(0, |length| {
+                CandidateWord::MAX >> (CandidateWord::BITS - length)
+            });
+
+        Self { path, run }
+    }
+}
+
+impl<const X: usize, const Y: usize> Rule<X, Y> for RenbanLine {
+    fn name(&self) -> &'static str {
+        "renban_line"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "a renban line holds a run of consecutive digits, so digits that can't be part of any run fitting the line are removed"
+    }
+
+    fn cost(&self) -> u32 {
+        3
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        Some(&self.path)
+    }
+
+    fn is_satisfied(&self, gameboard: &Gameboard<X, Y>) -> bool {
+        let mut digits: Vec<u8> = self
+            .path
+            .iter()
+            .map(|(x, y)| gameboard.cell(*x, *y))
+            .filter(|n| *n != 0)
+            .collect();
+        digits.sort_unstable();
+
+        // distinct digits spanning less than the path's length still fit a run
+        digits.windows(2).all(|pair| pair[1] > pair[0])
+            && digits
+                .first()
+                .zip(digits.last())
+                .is_none_or(|(low, high)| usize::from(high - low) < self.path.len())
+    }
+
+    // keep a digit only if some run of consecutive digits can be spread over the
+    // whole path with that digit in that cell
+    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
+        let length = self.path.len();
+
+        if self.run == 0 || length > Y {
+            return;
+        }
+
+        let masks: Vec<CandidateWord> = self
+            .path
+            .iter()
+            .map(|position| candidates.effective_mask(gameboard, *position))
+            .collect();
+
+        let mut allowed = vec![0; length];
+
+        for start in 0..=Y - length {
+            let run = self.run << start;
+
+            for (i, mask) in masks.iter().enumerate() {
+                for n in digits::<Y>() {
+                    let bit = n.to_cell_mask();
+
+                    if mask & run & bit > 0
+                        && can_assign(&masks, i, run & !bit, &mut vec![false; length])
+                    {
+                        allowed[i] |= bit;
+                    }
+                }
+            }
+        }
+
+        for ((x, y), allowed) in self.path.iter().zip(allowed) {
+            if gameboard.cell(*x, *y) != 0 {
+                continue;
+            }
+
+            for n in digits::<Y>() {
+                if allowed & n.to_cell_mask() == 0 {
+                    candidates.exclude_candidate(*x, *y, n);
+                }
+            }
+        }
+    }
+}
+
+// whether every cell other than `fixed` can take a distinct digit from `digits`
+fn can_assign(
+    masks: &[CandidateWord],
+    fixed: usize,
+    digits: CandidateWord,
+    used: &mut [bool],
+) -> bool {
+    let Some(i) = (0..masks.len()).find(|i| *i != fixed && !used[*i]) else {
+        return true;
+    };
+
+    used[i] = true;
+
+    for n in CandidateSet(masks[i] & digits).iter() {
+        let bit = n.to_cell_mask();
+
+        if can_assign(masks, fixed, digits & !bit, used) {
+            used[i] = false;
+            return true;
+        }
+    }
+
+    used[i] = false;
+
+    false
+}
+
+pub struct Quadruple {
+    cells: [(usize, usize); 4],
+    digits: Vec<u8>,
+}
+
+impl Quadruple {
+    #[must_use]
+    pub fn new((x, y): (usize, usize), digits: Vec<u8>) -> Self {
+        Self {
+            cells: [(x, y), (x, y + 1), (x + 1, y), (x + 1, y + 1)],
+            digits,
+        }
+    }
+
+    // cells sharing a row or column must differ; diagonal neighbours may repeat
+    fn is_feasible(&self, assignment: [u8; 4]) -> bool {
+        let [top_left, top_right, bottom_left, bottom_right] = assignment;
+
+        if top_left == top_right
+            || bottom_left == bottom_right
+            || top_left == bottom_left
+            || top_right == bottom_right
+        {
+            return false;
+        }
+
+        let mut remaining = assignment.to_vec();
+
+        self.digits.iter().all(|n| {
+            let Some(i) = remaining.iter().position(|m| m == n) else {
+                return false;
+            };
+            remaining.swap_remove(i);
+            true
+        })
+    }
+}
+
+impl<const X: usize, const Y: usize> Rule<X, Y> for Quadruple {
+    fn name(&self) -> &'static str {
+        "quadruple"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "the listed digits all appear in the 2x2 block, so digits that leave no room for them are removed"
+    }
+
+    fn cost(&self) -> u32 {
+        3
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        Some(&self.cells)
+    }
+
+    fn is_satisfied(&self, gameboard: &Gameboard<X, Y>) -> bool {
+        let assignment = self.cells.map(|(x, y)| gameboard.cell(x, y));
+        let empty = assignment.into_iter().filter(|n| *n == 0).count();
+
+        if empty == 0 {
+            return self.is_feasible(assignment);
+        }
+
+        // the empty cells have to be enough for the listed digits not placed yet
+        let mut missing = self.digits.clone();
+        for n in assignment {
+            if let Some(i) = missing.iter().position(|m| *m == n) {
+                missing.swap_remove(i);
+            }
+        }
+
+        missing.len() <= empty
+    }
+
+    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
+        let options: Vec<Vec<u8>> = self
+            .cells
+            .iter()
+            .map(|position| {
+                let mask = candidates.effective_mask(gameboard, *position);
+                digits::<Y>()
+                    .filter(|n| mask & n.to_cell_mask() > 0)
+                    .collect()
+            })
+            .collect();
+
+        let mut allowed = [0; 4];
+
+        for a in &options[0] {
+            for b in &options[1] {
+                for c in &options[2] {
+                    for d in &options[3] {
+                        if self.is_feasible([*a, *b, *c, *d]) {
+                            allowed[0] |= a.to_cell_mask();
+                            allowed[1] |= b.to_cell_mask();
+                            allowed[2] |= c.to_cell_mask();
+                            allowed[3] |= d.to_cell_mask();
+                        }
+                    }
+                }
+            }
+        }
+
+        for ((x, y), allowed) in self.cells.iter().zip(allowed) {
+            if gameboard.cell(*x, *y) != 0 {
+                continue;
+            }
+
+            for n in digits::<Y>() {
+                if allowed & n.to_cell_mask() == 0 {
+                    candidates.exclude_candidate(*x, *y, n);
+                }
+            }
+        }
+    }
+}
+
+pub struct AntiKnight;
+
+impl AntiKnight {
+    fn knight_moves<const X: usize, const Y: usize>(
+        (x, y): (usize, usize),
+    ) -> impl Iterator<Item = (usize, usize)> {
+        [
+            (1, 2),
+            (2, 1),
+            (2, -1),
+            (1, -2),
+            (-1, -2),
+            (-2, -1),
+            (-2, 1),
+            (-1, 2),
+        ]
+        .into_iter()
+        .filter_map(move |(dx, dy): (isize, isize)| {
+            let (x, y) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+            (x < X && y < Y).then_some((x, y))
+        })
+    }
+}
+
+impl<const X: usize, const Y: usize> Rule<X, Y> for AntiKnight {
+    fn name(&self) -> &'static str {
+        "anti_knight"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "cells a knight's move apart can't repeat a digit, so a placed digit is removed from the cells a knight's move away"
+    }
+
+    fn cost(&self) -> u32 {
+        2
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        None
+    }
+
+    fn is_satisfied(&self, gameboard: &Gameboard<X, Y>) -> bool {
+        (0..X)
+            .flat_map(|x| (0..Y).map(move |y| (x, y)))
+            .all(|(x, y)| {
+                let n = gameboard.cell(x, y);
+
+                n == 0
+                    || Self::knight_moves::<X, Y>((x, y))
+                        .all(|(x2, y2)| n != gameboard.cell(x2, y2))
+            })
+    }
+
+    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
+        for x in 0..X {
+            for y in 0..Y {
+                let n = gameboard.cell(x, y);
+
+                if n == 0 {
+                    continue;
+                }
+
+                for (x2, y2) in Self::knight_moves::<X, Y>((x, y)) {
+                    candidates.exclude_candidate(x2, y2, n);
+                }
+            }
+        }
+    }
+}
+
+pub struct KillerCage {
+    cage: Vec<(usize, usize)>,
+    sum: u32,
+}
+
+impl KillerCage {
+    #[must_use]
+    pub fn new(cage: Vec<(usize, usize)>, sum: u32) -> Self {
+        Self { cage, sum }
+    }
+}
+
+impl<const X: usize, const Y: usize> Rule<X, Y> for KillerCage {
+    fn name(&self) -> &'static str {
+        "killer_cage"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "a killer cage's digits differ and add up to its sum, so digits that can't be part of any such set are removed"
+    }
+
+    fn cost(&self) -> u32 {
+        3
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        Some(&self.cage)
+    }
+
+    fn is_satisfied(&self, gameboard: &Gameboard<X, Y>) -> bool {
+        let digits = self
+            .cage
+            .iter()
+            .map(|(x, y)| gameboard.cell(*x, *y))
+            .filter(|n| *n != 0);
+
+        let filled = digits.clone().count();
+        let distinct = digits
+            .clone()
+            .fold(0, |mask, n| mask | n.to_cell_mask())
+            .count_ones();
+        let sum: u32 = digits.map(u32::from).sum();
+
+        if filled == self.cage.len() {
+            return distinct as usize == filled && sum == self.sum;
+        }
+
+        // every empty cell adds at least one
+        let empty = u32::try_from(self.cage.len() - filled).unwrap_or(u32::MAX);
+        distinct as usize == filled && sum.saturating_add(empty) <= self.sum
+    }
+
+    // keep a digit only if the rest of the cage can make up the remaining sum with
+    // other digits
+    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
+        let masks: Vec<CandidateWord> = self
+            .cage
+            .iter()
+            .map(|position| candidates.effective_mask(gameboard, *position))
+            .collect();
+
+        for (i, (x, y)) in self.cage.iter().enumerate() {
+            if gameboard.cell(*x, *y) != 0 {
+                continue;
+            }
+
+            for n in digits::<Y>() {
+                let bit = n.to_cell_mask();
+
+                if masks[i] & bit == 0 {
+                    continue;
+                }
+
+                let fits = self.sum >= u32::from(n)
+                    && can_sum(
+                        &masks,
+                        i,
+                        bit,
+                        self.sum - u32::from(n),
+                        &mut vec![false; masks.len()],
+                    );
+
+                if !fits {
+                    candidates.exclude_candidate(*x, *y, n);
+                }
+            }
+        }
+    }
+}
+
+// whether every cell other than `fixed` can take a digit outside `used`, all distinct,
+// adding up to `remaining`
+fn can_sum(
+    masks: &[CandidateWord],
+    fixed: usize,
+    used: CandidateWord,
+    remaining: u32,
+    done: &mut [bool],
+) -> bool {
+    let Some(i) = (0..masks.len()).find(|i| *i != fixed && !done[*i]) else {
+        return remaining == 0;
+    };
+
+    done[i] = true;
+
+    for n in CandidateSet(masks[i] & !used).iter() {
+        if u32::from(n) > remaining {
+            break;
+        }
+
+        if can_sum(
+            masks,
+            fixed,
+            used | n.to_cell_mask(),
+            remaining - u32::from(n),
+            done,
+        ) {
+            done[i] = false;
+            return true;
+        }
+    }
+
+    done[i] = false;
+
+    false
+}
+
+// sums an X or V asks for: 10 and 5
+const XV_SUMS: [u8; 2] = [10, 5];
+
+pub struct XvSum {
+    cells: [(usize, usize); 2],
+    sum: u8,
+}
+
+impl XvSum {
+    #[must_use]
+    pub fn new(cells: [(usize, usize); 2], sum: u8) -> Self {
+        Self { cells, sum }
+    }
+}
+
+impl<const X: usize, const Y: usize> Rule<X, Y> for XvSum {
+    fn name(&self) -> &'static str {
+        "xv_sum"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "cells joined by an X add up to 10 and by a V to 5, so digits whose partner isn't a candidate next door are removed"
+    }
+
+    fn cost(&self) -> u32 {
+        1
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        Some(&self.cells)
+    }
+
+    fn is_satisfied(&self, gameboard: &Gameboard<X, Y>) -> bool {
+        let [(x, y), (x2, y2)] = self.cells;
+        let (a, b) = (gameboard.cell(x, y), gameboard.cell(x2, y2));
+        a == 0 || b == 0 || a + b == self.sum
+    }
+
+    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
+        for (cell, partner) in [(0, 1), (1, 0)] {
+            let (x, y) = self.cells[cell];
+
+            if gameboard.cell(x, y) != 0 {
+                continue;
+            }
+
+            let mask = candidates.effective_mask(gameboard, self.cells[partner]);
+
+            for n in digits::<Y>() {
+                // neighbours share a row or column, so can't both be half the sum
+                let fits =
+                    n < self.sum && self.sum - n != n && (self.sum - n).to_cell_mask() & mask > 0;
+
+                if !fits {
+                    candidates.exclude_candidate(x, y, n);
+                }
+            }
+        }
+    }
+}
+
+// no two orthogonal neighbours without a mark add up to 10 or 5
+pub struct XvNegative {
+    marked: Vec<[(usize, usize); 2]>,
+}
+
+impl XvNegative {
+    #[must_use]
+    pub fn new(marked: Vec<[(usize, usize); 2]>) -> Self {
+        Self { marked }
+    }
+
+    fn unmarked_neighbours<const X: usize, const Y: usize>(
+        &self,
+        (x, y): (usize, usize),
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ]
+        .into_iter()
+        .filter(|(x2, y2)| *x2 < X && *y2 < Y)
+        .filter(move |neighbour| {
+            !self
+                .marked
+                .iter()
+                .any(|pair| *pair == [(x, y), *neighbour] || *pair == [*neighbour, (x, y)])
+        })
+    }
+}
+
+impl<const X: usize, const Y: usize> Rule<X, Y> for XvNegative {
+    fn name(&self) -> &'static str {
+        "xv_negative"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "neighbours without an X or V don't add up to 10 or 5, so a placed digit removes its partners for those sums from unmarked neighbours"
+    }
+
+    fn cost(&self) -> u32 {
+        2
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        None
+    }
+
+    fn is_satisfied(&self, gameboard: &Gameboard<X, Y>) -> bool {
+        (0..X)
+            .flat_map(|x| (0..Y).map(move |y| (x, y)))
+            .all(|(x, y)| {
+                self.unmarked_neighbours::<X, Y>((x, y)).all(|(x2, y2)| {
+                    let (a, b) = (gameboard.cell(x, y), gameboard.cell(x2, y2));
+                    a == 0 || b == 0 || !XV_SUMS.contains(&(a + b))
+                })
+            })
+    }
+
+    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
+        for x in 0..X {
+            for y in 0..Y {
+                let n = gameboard.cell(x, y);
+
+                if n == 0 {
+                    continue;
+                }
+
+                for (x2, y2) in self.unmarked_neighbours::<X, Y>((x, y)) {
+                    for sum in XV_SUMS.into_iter().filter(|sum| *sum > n) {
+                        candidates.exclude_candidate(x2, y2, sum - n);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct CloneRegion {
+    pairs: Vec<((usize, usize), (usize, usize))>,
+    // both groups, for `scope`
+    cells: Vec<(usize, usize)>,
+}
+
+impl CloneRegion {
+    #[must_use]
+    pub fn new(cells: Vec<(usize, usize)>, twin: Vec<(usize, usize)>) -> Self {
+        Self {
+            pairs: cells.iter().copied().zip(twin.iter().copied()).collect(),
+            cells: cells.into_iter().chain(twin).collect(),
+        }
+    }
+}
+
+impl<const X: usize, const Y: usize> Rule<X, Y> for CloneRegion {
+    fn name(&self) -> &'static str {
+        "clone_region"
+    }
+
+    fn explanation(&self) -> &'static str {
+        "cloned cells hold the same digit as their twins, so each keeps only the candidates its twin also has"
+    }
+
+    fn cost(&self) -> u32 {
+        1
+    }
+
+    fn scope(&self) -> Option<&[(usize, usize)]> {
+        Some(&self.cells)
+    }
+
+    fn is_satisfied(&self, gameboard: &Gameboard<X, Y>) -> bool {
+        self.pairs.iter().all(|((x, y), (x2, y2))| {
+            let (a, b) = (gameboard.cell(*x, *y), gameboard.cell(*x2, *y2));
+            a == 0 || b == 0 || a == b
+        })
+    }
+
+    fn visit(&self, gameboard: &Gameboard<X, Y>, candidates: &mut Candidates<X, Y>) {
+        for (a, b) in &self.pairs {
+            let shared =
+                candidates.effective_mask(gameboard, *a) & candidates.effective_mask(gameboard, *b);
+
+            for (x, y) in [*a, *b] {
+                if gameboard.cell(x, y) != 0 {
+                    continue;
+                }
+
+                for n in digits::<Y>().filter(|n| n.to_cell_mask() & shared == 0) {
+                    candidates.exclude_candidate(x, y, n);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visited(rule: &dyn Rule<9, 9>, gameboard: &Gameboard<9, 9>) -> Candidates<9, 9> {
+        let mut candidates = Candidates::default();
+        rule.visit(gameboard, &mut candidates);
+        candidates
+    }
+
+    fn digits_at(candidates: &Candidates<9, 9>, position: (usize, usize)) -> Vec<u8> {
+        candidates.candidates_at(position).iter().collect()
+    }
+
+    #[test]
+    fn whispers_keep_neighbours_apart() {
+        let line = WhisperLine::new(vec![(0, 0), (0, 1)], 5);
+        let mut state = [[0; 9]; 9];
+
+        // nothing is 5 away from a 5, either way
+        let empty = Gameboard::from(state);
+        assert_eq!(
+            digits_at(&visited(&line, &empty), (0, 0)),
+            [1, 2, 3, 4, 6, 7, 8, 9]
+        );
+
+        state[0][0] = 3;
+        let gameboard = Gameboard::from(state);
+        assert_eq!(digits_at(&visited(&line, &gameboard), (0, 1)), [8, 9]);
+        assert!(Rule::<9, 9>::is_satisfied(&line, &gameboard));
+
+        state[0][1] = 7;
+        assert!(!Rule::<9, 9>::is_satisfied(&line, &Gameboard::from(state)));
+    }
+
+    #[test]
+    fn whispers_too_wide_for_any_digits_empty_their_cells() {
+        let line = WhisperLine::new(vec![(0, 0), (0, 1)], u8::MAX);
+
+        assert!(visited(&line, &Gameboard::from([[0; 9]; 9]))
+            .candidates_at((0, 0))
+            .is_empty());
+    }
+
+    #[test]
+    fn renbans_hold_a_run() {
+        let line = RenbanLine::new(vec![(0, 0), (0, 1), (0, 2)]);
+        let mut state = [[0; 9]; 9];
+        state[0][0] = 1;
+        let gameboard = Gameboard::from(state);
+
+        assert_eq!(digits_at(&visited(&line, &gameboard), (0, 1)), [2, 3]);
+        assert_eq!(digits_at(&visited(&line, &gameboard), (0, 2)), [2, 3]);
+
+        state[0][2] = 4;
+        assert!(!Rule::<9, 9>::is_satisfied(&line, &Gameboard::from(state)));
+    }
+
+    #[test]
+    fn renbans_as_long_as_a_row_of_the_widest_board_keep_every_digit() {
+        let line = RenbanLine::new((0..32).map(|y| (0, y)).collect());
+        let gameboard = Gameboard::from([[0; 32]; 32]);
+        let mut candidates = Candidates::<32, 32>::default();
+
+        line.visit(&gameboard, &mut candidates);
+
+        assert_eq!(candidates.candidates_at((0, 31)).len(), 32);
+    }
+
+    #[test]
+    fn quadruples_leave_room_for_their_digits() {
+        let quadruple = Quadruple::new((0, 0), vec![1, 2, 3]);
+        let mut state = [[0; 9]; 9];
+        state[0][0] = 5;
+        let gameboard = Gameboard::from(state);
+        let candidates = visited(&quadruple, &gameboard);
+
+        for position in [(0, 1), (1, 0), (1, 1)] {
+            assert_eq!(digits_at(&candidates, position), [1, 2, 3]);
+        }
+
+        state[0][1] = 6;
+        assert!(!Rule::<9, 9>::is_satisfied(
+            &quadruple,
+            &Gameboard::from(state)
+        ));
+    }
+
+    #[test]
+    fn diagonals_exclude_along_both_long_diagonals() {
+        let mut state = [[0; 9]; 9];
+        state[0][0] = 7;
+        state[0][8] = 3;
+        let gameboard = Gameboard::from(state);
+        let mut candidates = Candidates::default();
+
+        for rule in Variant::Diagonals.to_rules::<9, 9>() {
+            rule.visit(&gameboard, &mut candidates);
+        }
+
+        assert!(!candidates.candidates_at((8, 8)).contains(7));
+        assert!(!candidates.candidates_at((8, 0)).contains(3));
+        assert!(candidates.candidates_at((8, 0)).contains(7));
+    }
+
+    #[test]
+    fn anti_knight_excludes_a_knights_move_away() {
+        let mut state = [[0; 9]; 9];
+        state[4][4] = 5;
+        let candidates = visited(&AntiKnight, &Gameboard::from(state));
+
+        assert!(!candidates.candidates_at((2, 3)).contains(5));
+        assert!(!candidates.candidates_at((5, 6)).contains(5));
+        assert!(candidates.candidates_at((3, 3)).contains(5));
+
+        state[6][5] = 5;
+        assert!(!Rule::<9, 9>::is_satisfied(
+            &AntiKnight,
+            &Gameboard::from(state)
+        ));
+    }
+
+    #[test]
+    fn killer_cages_keep_the_digits_that_make_their_sum() {
+        let cage = vec![(0, 0), (0, 1)];
+        let empty = Gameboard::from([[0; 9]; 9]);
+
+        let low = visited(&KillerCage::new(cage.clone(), 3), &empty);
+        assert_eq!(digits_at(&low, (0, 0)), [1, 2]);
+        let high = visited(&KillerCage::new(cage.clone(), 17), &empty);
+        assert_eq!(digits_at(&high, (0, 1)), [8, 9]);
+
+        let mut state = [[0; 9]; 9];
+        state[0][0] = 4;
+        state[0][1] = 4;
+        assert!(!Rule::<9, 9>::is_satisfied(
+            &KillerCage::new(cage, 8),
+            &Gameboard::from(state)
+        ));
+    }
+
+    #[test]
+    fn xv_pairs_keep_digits_with_a_partner() {
+        let mut state = [[0; 9]; 9];
+        let v = XvSum::new([(0, 0), (0, 1)], 5);
+        assert_eq!(
+            digits_at(&visited(&v, &Gameboard::from(state)), (0, 0)),
+            [1, 2, 3, 4]
+        );
+
+        // an unmarked neighbour of a 3 can't be a 7 or a 2
+        state[4][4] = 3;
+        let negative = visited(&XvNegative::new(vec![]), &Gameboard::from(state));
+        assert_eq!(digits_at(&negative, (4, 5)), [1, 3, 4, 5, 6, 8, 9]);
+
+        state[0][0] = 1;
+        state[0][1] = 3;
+        assert!(!Rule::<9, 9>::is_satisfied(&v, &Gameboard::from(state)));
+    }
+
+    #[test]
+    fn clones_share_their_twins_candidates() {
+        let clone = CloneRegion::new(vec![(0, 0), (0, 1)], vec![(8, 7), (8, 8)]);
+        let mut state = [[0; 9]; 9];
+        state[0][0] = 4;
+        let gameboard = Gameboard::from(state);
+        let mut candidates = Candidates::default();
+        candidates.exclude_candidate(8, 8, 1);
+
+        clone.visit(&gameboard, &mut candidates);
+
+        assert_eq!(digits_at(&candidates, (8, 7)), [4]);
+        assert!(!candidates.candidates_at((0, 1)).contains(1));
+
+        state[8][7] = 5;
+        assert!(!Rule::<9, 9>::is_satisfied(&clone, &Gameboard::from(state)));
+    }
+}